@@ -0,0 +1,59 @@
+#![allow(non_snake_case)]
+
+//! Helpers shared by the benchmarks
+
+use ark_bls12_381::Fr;
+use ark_std::{rand::Rng, test_rng, UniformRand};
+
+use multifolding_poc::ccs::ccs::CCS;
+use multifolding_poc::util::vec::SparseMatrix;
+
+/// Return an R1CS-shaped CCS of 2^log_m constraints and 2^log_n variables, whose matrices have
+/// `row_weight` random nonzero entries per row, as R1CS matrices usually do
+pub fn get_random_ccs(log_m: usize, log_n: usize, row_weight: usize) -> CCS<Fr> {
+    let mut rng = test_rng();
+    let (n_rows, n_cols) = (1 << log_m, 1 << log_n);
+    let mut random_matrix = || SparseMatrix::<Fr> {
+        n_rows,
+        n_cols,
+        coeffs: (0..n_rows)
+            .map(|_| {
+                (0..row_weight)
+                    .map(|_| (Fr::rand(&mut rng), rng.gen_range(0..n_cols)))
+                    .collect()
+            })
+            .collect(),
+    };
+    let A = random_matrix();
+    let B = random_matrix();
+    let C = random_matrix();
+    CCS::from_r1cs(A, B, C, 1).unwrap()
+}
+
+/// Return a CCS of 2^log_m constraints and 2^log_n variables with t random matrices, whose
+/// multisets multiply them two by two, to benchmark the per-matrix work as t grows
+#[allow(dead_code)]
+pub fn get_random_ccs_with_t(log_m: usize, log_n: usize, row_weight: usize, t: usize) -> CCS<Fr> {
+    let mut rng = test_rng();
+    let (n_rows, n_cols) = (1 << log_m, 1 << log_n);
+    let M: Vec<SparseMatrix<Fr>> = (0..t)
+        .map(|_| SparseMatrix::<Fr> {
+            n_rows,
+            n_cols,
+            coeffs: (0..n_rows)
+                .map(|_| {
+                    (0..row_weight)
+                        .map(|_| (Fr::rand(&mut rng), rng.gen_range(0..n_cols)))
+                        .collect()
+                })
+                .collect(),
+        })
+        .collect();
+    let S: Vec<Vec<usize>> = (0..t)
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .map(|S_i| S_i.to_vec())
+        .collect();
+    let c = (0..S.len()).map(|_| Fr::rand(&mut rng)).collect();
+    CCS::new(M, S, c, 1).unwrap()
+}
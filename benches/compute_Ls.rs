@@ -0,0 +1,44 @@
+#![allow(non_snake_case)]
+
+//! Benchmark of the computation of the L_j(x) polynomials of an LCCCS instance, for the t = 3
+//! matrices of an R1CS against a synthetic CCS of t = 10 matrices.
+//!
+//! Run it with `cargo bench --bench compute_Ls` for the parallel version, and with
+//! `cargo bench --bench compute_Ls --no-default-features` for the serial one.
+
+use ark_bls12_381::{Fr, G1Projective};
+use ark_std::{test_rng, UniformRand};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use multifolding_poc::ccs::pedersen::Pedersen;
+
+mod common;
+use common::{get_random_ccs, get_random_ccs_with_t};
+
+fn bench_compute_Ls(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_Ls");
+    group.sample_size(10);
+
+    for log_size in [10, 12] {
+        for (t, ccs) in [
+            (3, get_random_ccs(log_size, log_size, 3)),
+            (10, get_random_ccs_with_t(log_size, log_size, 3, 10)),
+        ] {
+            let mut rng = test_rng();
+            let z: Vec<Fr> = (0..ccs.n).map(|_| Fr::rand(&mut rng)).collect();
+            let pedersen_params =
+                Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+            let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("t={}", t), log_size),
+                &z,
+                |b, z| b.iter(|| lcccs.compute_Ls(z).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_compute_Ls);
+criterion_main!(benches);
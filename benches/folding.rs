@@ -0,0 +1,95 @@
+#![allow(non_snake_case)]
+
+//! Benchmark of the steps of folding an LCCCS and a CCCS instance, as the CCS size grows: the
+//! commitment to the witness, the computation of the sigmas and thetas (from scratch and out of
+//! the prepared LCCCS witness), and the fold of the instances and witnesses.
+//!
+//! Run it with `cargo bench --bench folding`.
+
+use ark_bls12_381::{Fr, G1Projective};
+use ark_std::{test_rng, UniformRand};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use multifolding_poc::ccs::pedersen::Pedersen;
+use multifolding_poc::ccs::util::PreparedWitness;
+use multifolding_poc::multifolding::{FoldProof, Multifolding};
+
+mod common;
+use common::get_random_ccs;
+
+type NIMFS = Multifolding<G1Projective>;
+
+fn bench_folding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("folding");
+    group.sample_size(10);
+
+    for log_size in [8, 10, 12] {
+        let ccs = get_random_ccs(log_size, log_size, 3);
+        let n = ccs.n;
+
+        let mut rng = test_rng();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        // the instances do not need to satisfy the relation to measure the cost of folding them
+        let z_1: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let z_2: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let (lcccs, w_1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
+        let (cccs, w_2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
+
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let rho = Fr::rand(&mut rng);
+
+        group.bench_with_input(BenchmarkId::new("commit", n), &w_1, |b, w_1| {
+            b.iter(|| Pedersen::<G1Projective>::commit(&pedersen_params, &w_1.w, &w_1.r_w).unwrap())
+        });
+
+        let z_lcccs = vec![z_1];
+        let z_cccs = vec![z_2];
+        group.bench_with_input(BenchmarkId::new("sigmas_and_thetas", n), &ccs, |b, ccs| {
+            b.iter(|| NIMFS::compute_sigmas_and_thetas(ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap())
+        });
+
+        let prepared_lcccs: Vec<PreparedWitness<Fr>> = z_lcccs
+            .iter()
+            .map(|z| PreparedWitness::new(&ccs.M, z, ccs.s))
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::new("sigmas_and_thetas_prepared", n),
+            &ccs,
+            |b, ccs| {
+                b.iter(|| {
+                    NIMFS::compute_sigmas_and_thetas_prepared(
+                        ccs,
+                        &prepared_lcccs,
+                        &z_cccs,
+                        &r_x_prime,
+                    )
+                    .unwrap()
+                })
+            },
+        );
+
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap();
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime,
+        };
+        let lcccs = vec![lcccs];
+        let cccs = vec![cccs];
+        let w_lcccs = vec![w_1];
+        let w_cccs = vec![w_2];
+        group.bench_function(BenchmarkId::new("fold", n), |b| {
+            b.iter(|| {
+                let folded = NIMFS::fold(&lcccs, &cccs, &fold_proof, rho).unwrap();
+                let w_folded = NIMFS::fold_witness(&w_lcccs, &w_cccs, rho);
+                (folded, w_folded)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_folding);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+//! Benchmark of the MSM-based Pedersen commitment against a naive sum of scalar multiplications,
+//! and of the timing of the commitment depending on the committed values.
+
+use ark_bls12_381::{Fr, G1Projective};
+use ark_ec::AffineRepr;
+use ark_std::{test_rng, One, UniformRand, Zero};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use multifolding_poc::ccs::pedersen::Pedersen;
+
+fn bench_pedersen_commit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pedersen_commit");
+    group.sample_size(10);
+
+    let n: usize = 4096;
+    let mut rng = test_rng();
+    let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+    let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+    let r = Fr::rand(&mut rng);
+
+    group.bench_with_input(BenchmarkId::new("msm", n), &v, |b, v| {
+        b.iter(|| Pedersen::<G1Projective>::commit(&params, v, &r).unwrap())
+    });
+    // the naive version skips the blinding term, which is a single scalar multiplication
+    group.bench_with_input(BenchmarkId::new("naive", n), &v, |b, v| {
+        b.iter(|| {
+            params
+                .generators
+                .iter()
+                .zip(v.iter())
+                .fold(G1Projective::zero(), |acc, (g_i, v_i)| {
+                    acc + g_i.into_group() * v_i
+                })
+        })
+    });
+    group.finish();
+}
+
+/// The commitment is not constant time, so committing to vectors of zeros or small values is
+/// faster than committing to random vectors
+fn bench_pedersen_commit_timing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pedersen_commit_timing");
+    group.sample_size(10);
+
+    let n: usize = 4096;
+    let mut rng = test_rng();
+    let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+    let r = Fr::rand(&mut rng);
+
+    let inputs = [
+        ("zeros", vec![Fr::zero(); n]),
+        ("ones", vec![Fr::one(); n]),
+        ("random", (0..n).map(|_| Fr::rand(&mut rng)).collect()),
+    ];
+    for (name, v) in inputs.iter() {
+        group.bench_with_input(BenchmarkId::new(*name, n), v, |b, v| {
+            b.iter(|| Pedersen::<G1Projective>::commit(&params, v, &r).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pedersen_commit, bench_pedersen_commit_timing);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+#![allow(non_snake_case)]
+
+//! Benchmark of building the q(x) polynomial for many z vectors of the same CCS, preparing its
+//! multiset product structure for each z vector against reusing one plan for all of them.
+//!
+//! Run it with `cargo bench --bench q`.
+
+use ark_bls12_381::Fr;
+use ark_std::{test_rng, UniformRand};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+mod common;
+use common::get_random_ccs;
+
+fn bench_q_from_plan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("q");
+    group.sample_size(10);
+
+    let n_witnesses = 16;
+    for log_size in [8, 10, 12] {
+        let ccs = get_random_ccs(log_size, log_size, 3);
+
+        let mut rng = test_rng();
+        let zs: Vec<Vec<Fr>> = (0..n_witnesses)
+            .map(|_| (0..ccs.n).map(|_| Fr::rand(&mut rng)).collect())
+            .collect();
+
+        group.bench_with_input(
+            BenchmarkId::new("prepare_each_time", ccs.n),
+            &zs,
+            |b, zs| {
+                b.iter(|| {
+                    zs.iter()
+                        .map(|z| ccs.q_from_plan(&ccs.prepare_q(), z))
+                        .collect::<Vec<_>>()
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("reused_plan", ccs.n), &zs, |b, zs| {
+            let plan = ccs.prepare_q();
+            b.iter(|| {
+                zs.iter()
+                    .map(|z| ccs.q_from_plan(&plan, z))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_q_from_plan);
+criterion_main!(benches);
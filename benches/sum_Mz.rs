@@ -0,0 +1,73 @@
+#![allow(non_snake_case)]
+
+//! Benchmark of the computation of the v_j values (\sum_{y} M_j(r, y) * z(y)).
+//!
+//! Run it with `cargo bench --bench sum_Mz` for the parallel version, and with
+//! `cargo bench --bench sum_Mz --no-default-features` for the serial one.
+
+use ark_bls12_381::Fr;
+use ark_std::{test_rng, UniformRand};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use multifolding_poc::ccs::util::{compute_all_sum_Mz_evals, PreparedWitness};
+
+mod common;
+use common::get_random_ccs;
+
+fn bench_compute_all_sum_Mz_evals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_all_sum_Mz_evals");
+    for log_size in [10, 12, 14, 16] {
+        let ccs = get_random_ccs(log_size, log_size, 3);
+
+        let mut rng = test_rng();
+        let z: Vec<Fr> = (0..ccs.n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(log_size), &ccs, |b, ccs| {
+            b.iter(|| compute_all_sum_Mz_evals(&ccs.M, &z, &r, ccs.s).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Evaluate the same z vector at many points, recomputing the M_j * z products for each point
+/// against computing them once in a PreparedWitness
+fn bench_prepared_witness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prepared_witness");
+    group.sample_size(10);
+
+    let n_points = 16;
+    for log_size in [10, 12, 14] {
+        let ccs = get_random_ccs(log_size, log_size, 3);
+
+        let mut rng = test_rng();
+        let z: Vec<Fr> = (0..ccs.n).map(|_| Fr::rand(&mut rng)).collect();
+        let rs: Vec<Vec<Fr>> = (0..n_points)
+            .map(|_| (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect())
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("recompute", log_size), &ccs, |b, ccs| {
+            b.iter(|| {
+                rs.iter()
+                    .map(|r| compute_all_sum_Mz_evals(&ccs.M, &z, r, ccs.s).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("prepared", log_size), &ccs, |b, ccs| {
+            b.iter(|| {
+                let prepared = PreparedWitness::new(&ccs.M, &z, ccs.s);
+                rs.iter()
+                    .map(|r| prepared.evaluate(r).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_compute_all_sum_Mz_evals,
+    bench_prepared_witness
+);
+criterion_main!(benches);
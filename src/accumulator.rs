@@ -0,0 +1,164 @@
+use ark_ec::CurveGroup;
+use transcript::IOPTranscript;
+
+use crate::ccs::cccs::{Witness, CCCS};
+use crate::ccs::ccs::CCSError;
+use crate::ccs::commitment::CommitmentScheme;
+use crate::ccs::lcccs::LCCCS;
+use crate::ccs::pedersen::Pedersen;
+use crate::multifolding::{Multifolding, MultifoldingError, Proof};
+
+/// Running LCCCS instance and witness into which CCCS instances are folded one step at a time, as
+/// in an IVC loop.
+///
+/// The same transcript has to be passed to all the [`Accumulator::fold_in`] calls, so that a
+/// verifier can replay the folds with [`Multifolding::verify`] on its own transcript, feeding it
+/// the returned proofs in order.
+#[derive(Debug)]
+pub struct Accumulator<C: CurveGroup, CS: CommitmentScheme<C> = Pedersen<C>> {
+    pub lcccs: LCCCS<C, CS>,
+    pub w: Witness<C::ScalarField>,
+    // number of CCCS instances folded in so far
+    fold_count: usize,
+    // digest of the CCS of the running instance, which folding never changes, so that it is not
+    // hashed again on each fold
+    ccs_digest: [u8; 32],
+}
+
+impl<C: CurveGroup, CS: CommitmentScheme<C>> Accumulator<C, CS> {
+    /// Start accumulating from the given LCCCS instance and its witness
+    pub fn new(lcccs: LCCCS<C, CS>, w: Witness<C::ScalarField>) -> Self {
+        let ccs_digest = lcccs.ccs.digest();
+        Self {
+            lcccs,
+            w,
+            fold_count: 0,
+            ccs_digest,
+        }
+    }
+
+    /// Return the number of CCCS instances folded into the accumulator, which is the number of
+    /// IVC steps when folding one instance per step. Unlike the relaxation factor u, which is a
+    /// field element that grows by rho on each fold, this is an actual count.
+    pub fn num_folds(&self) -> usize {
+        self.fold_count
+    }
+
+    /// Fold the CCCS instance into the running LCCCS instance, updating the running instance and
+    /// witness with the folded ones
+    ///
+    /// Return the multifolding proof of this step, or an error if the CCCS instance does not share
+    /// the CCS structure of the running instance or is not satisfied by its witness, in which case
+    /// the accumulator is left untouched.
+    pub fn fold_in(
+        &mut self,
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        cs_params: &CS::Params,
+        cccs: &CCCS<C, CS>,
+        w: &Witness<C::ScalarField>,
+    ) -> Result<Proof<C>, MultifoldingError> {
+        if cccs.ccs != self.lcccs.ccs {
+            return Err(CCSError::IncompatibleCCS.into());
+        }
+        cccs.check_relation(cs_params, w)?;
+
+        let (proof, folded_lcccs, folded_witness) = Multifolding::<C, CS>::prove_with_ccs_digest(
+            transcript,
+            &self.ccs_digest,
+            &[self.lcccs.clone()],
+            &[cccs.clone()],
+            &[self.w.clone()],
+            &[w.clone()],
+        )?;
+        self.lcccs = folded_lcccs;
+        self.w = folded_witness;
+        self.fold_count += 1;
+
+        Ok(proof)
+    }
+
+    /// Check that the running LCCCS instance is satisfied by the running witness
+    pub fn verify(&self, cs_params: &CS::Params) -> Result<(), CCSError> {
+        self.lcccs.check_relation(cs_params, &self.w)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::ccs::ccs::test::{get_test_ccs, get_test_z};
+    use ark_std::test_rng;
+
+    use ark_bls12_381::{Fr, G1Projective};
+
+    /// Fold several CCCS instances into the accumulator, replaying each step on the verifier side
+    #[test]
+    fn test_accumulator() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (lcccs, w) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(2))
+            .unwrap();
+        let mut accumulator = Accumulator::<G1Projective>::new(lcccs.clone(), w);
+
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_v.append_message(b"init", b"init").unwrap();
+        let mut running_instance_v = lcccs;
+
+        for i in 3..8 {
+            let (cccs, w) = ccs
+                .to_cccs(&mut rng, &pedersen_params, &get_test_z(i))
+                .unwrap();
+            let proof = accumulator
+                .fold_in(&mut transcript_p, &pedersen_params, &cccs, &w)
+                .unwrap();
+
+            running_instance_v = Multifolding::<G1Projective>::verify(
+                &mut transcript_v,
+                &[running_instance_v],
+                &[cccs],
+                proof,
+            )
+            .unwrap();
+            assert_eq!(running_instance_v, accumulator.lcccs);
+            assert_eq!(accumulator.num_folds(), i - 2);
+        }
+
+        accumulator.verify(&pedersen_params).unwrap();
+    }
+
+    /// A CCCS instance that is not satisfied by its witness is rejected before folding
+    #[test]
+    fn test_accumulator_bad_cccs() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (lcccs, w) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(2))
+            .unwrap();
+        let mut accumulator = Accumulator::<G1Projective>::new(lcccs.clone(), w);
+
+        let mut bad_z = get_test_z(3);
+        bad_z[3] += Fr::from(1u64);
+        let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &bad_z).unwrap();
+
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        assert!(matches!(
+            accumulator.fold_in(&mut transcript_p, &pedersen_params, &cccs, &w),
+            Err(MultifoldingError::CCS(CCSError::NotSatisfied { .. }))
+        ));
+        assert_eq!(accumulator.lcccs, lcccs);
+        assert_eq!(accumulator.num_folds(), 0);
+        accumulator.verify(&pedersen_params).unwrap();
+    }
+}
@@ -1,94 +1,242 @@
+use alloc::sync::Arc;
 use ark_ec::CurveGroup;
 use ark_ff::PrimeField;
-use ark_std::One;
-use ark_std::Zero;
-use std::ops::Add;
-use std::sync::Arc;
+use ark_poly::DenseMultilinearExtension;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{One, Zero};
 
-use ark_std::{rand::Rng, UniformRand};
+use ark_std::{cfg_iter, rand::Rng, UniformRand};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use subroutines::PolyIOP;
+use transcript::IOPTranscript;
 
 use crate::ccs::ccs::{CCSError, CCS};
+use crate::ccs::lcccs::LCCCS;
 use crate::ccs::util::compute_sum_Mz;
 
-use crate::ccs::pedersen::{Commitment, Params as PedersenParams, Pedersen};
-use crate::espresso::virtual_polynomial::VirtualPolynomial;
-use crate::util::hypercube::BooleanHypercube;
-use crate::util::mle::matrix_to_mle;
-use crate::util::mle::vec_to_mle;
+use crate::ccs::commitment::{CommitmentParams, CommitmentScheme};
+use crate::ccs::pedersen::Pedersen;
+use crate::espresso::sum_check::structs::IOPProof as SumCheckProof;
+use crate::espresso::sum_check::SumCheck;
+use crate::espresso::virtual_polynomial::{eq_eval, VPAuxInfo, VirtualPolynomial};
+
+use core::fmt;
+use core::marker::PhantomData;
 
-/// Witness for the LCCCS & CCCS, containing the w vector, and the r_w used as randomness in the Pedersen commitment.
-#[derive(Debug, Clone)]
+/// Witness for the LCCCS & CCCS, containing the w vector, and the r_w used as randomness in the commitment.
+#[derive(Debug, Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "zeroize", derive(zeroize::Zeroize, zeroize::ZeroizeOnDrop))]
 pub struct Witness<F: PrimeField> {
     pub w: Vec<F>,
-    pub r_w: F, // randomness used in the Pedersen commitment of w
+    pub r_w: F, // randomness used in the commitment of w
 }
 
 /// Committed CCS instance
-#[derive(Debug, Clone)]
-pub struct CCCS<C: CurveGroup> {
-    // Underlying CCS structure
-    pub ccs: CCS<C>,
-
-    // Commitment to witness
-    pub C: Commitment<C>,
-    // Public input/output
+///
+/// Unlike an LCCCS, a CCCS has no relaxation factor: its z vector is always (1, x, w), which is the
+/// same as an LCCCS with u = 1. Use [`CCCS::z`] to build it instead of concatenating the vectors.
+///
+/// Like for [`LCCCS`], the `Debug` and `Display` outputs only give the dimensions of the CCS, and
+/// [`CCCS::debug_verbose`] also prints the matrices.
+#[derive(Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CCCS<C: CurveGroup, CS: CommitmentScheme<C> = Pedersen<C>> {
+    /// Underlying CCS structure, shared with the other instances of the same CCS
+    pub ccs: Arc<CCS<C::ScalarField>>,
+
+    /// Commitment to witness
+    pub C: CS::Commitment,
+    /// Public input/output
     pub x: Vec<C::ScalarField>,
 }
 
-impl<C: CurveGroup> CCS<C> {
-    pub fn to_cccs<R: Rng>(
+impl<C: CurveGroup, CS: CommitmentScheme<C>> fmt::Debug for CCCS<C, CS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CCCS")
+            .field("ccs", &format_args!("CCS {{ {} }}", self.ccs.dimensions()))
+            .field("C", &self.C)
+            .field("x", &self.x)
+            .finish()
+    }
+}
+
+impl<C: CurveGroup, CS: CommitmentScheme<C>> fmt::Display for CCCS<C, CS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CCCS of a CCS with {}: C = {:?}, |x| = {}",
+            self.ccs.dimensions(),
+            self.C,
+            self.x.len()
+        )
+    }
+}
+
+/// Precomputed shape of the q(x) polynomial of a CCS structure: which matrices appear in the
+/// multisets and how they multiply together. It only depends on the CCS structure, so it can be
+/// computed once with [`CCS::prepare_q`] and reused with [`CCS::q_from_plan`] for many z vectors.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QPlan<F: PrimeField> {
+    /// Indices of the matrices appearing in the multisets, without repetitions
+    matrices: Vec<usize>,
+    /// For each multiset S_i, its coefficient c_i and the positions in `matrices` of its M_j
+    terms: Vec<(F, Vec<usize>)>,
+}
+
+impl<F: PrimeField> CCS<F> {
+    /// Precompute the multiset product structure of q(x)
+    pub fn prepare_q(&self) -> QPlan<F> {
+        let mut matrices: Vec<usize> = self.S.iter().flatten().copied().collect();
+        matrices.sort_unstable();
+        matrices.dedup();
+
+        let terms = self
+            .S
+            .iter()
+            .zip(self.c.iter())
+            .map(|(S_i, c_i)| {
+                let positions = S_i
+                    .iter()
+                    .map(|j| matrices.binary_search(j).unwrap())
+                    .collect();
+                (*c_i, positions)
+            })
+            .collect();
+
+        QPlan { matrices, terms }
+    }
+
+    /// Build q(x) for the given z vector out of a plan computed by [`CCS::prepare_q`]. Each
+    /// \sum_{y \in {0,1}^s'} M_j(x, y) * z(y) polynomial is computed once, even if M_j appears in
+    /// several multisets.
+    pub fn q_from_plan(&self, plan: &QPlan<F>, z: &[F]) -> VirtualPolynomial<F> {
+        let sum_Mzs: Vec<Arc<DenseMultilinearExtension<F>>> = cfg_iter!(plan.matrices)
+            .map(|j| Arc::new(compute_sum_Mz(&self.M[*j], z, self.s)))
+            .collect();
+
+        let mut q = VirtualPolynomial::<F>::new(self.s);
+        for (c_i, positions) in plan.terms.iter() {
+            // the MLEs are shared through their Arc, so the virtual polynomial stores each of them
+            // only once
+            q.add_mle_list(positions.iter().map(|p| sum_Mzs[*p].clone()), *c_i)
+                .unwrap();
+        }
+        q
+    }
+
+    /// Commit to each matrix M_j through the evaluation table of its MLE M_j(x, y) over
+    /// {0,1}^{s+s'}, where the entry (i, j) of the matrix is at the index i * 2^s' + j, so that a
+    /// preprocessing verifier can hold the commitments instead of the matrices. The matrices are
+    /// public, so the commitments are not hiding (r = 0).
+    ///
+    /// The commitment scheme parameters must be of length 2^{s+s'}. The commitments are returned
+    /// instead of being stored on the CCS: `CCS<F>` is only generic over the field, while the
+    /// commitments depend on the group and on the commitment scheme, so storing them would make
+    /// every CCS generic over both. The preprocessing verifier keeps them next to the CCS, which
+    /// keeps its matrices for the prover.
+    pub fn commit_matrices<C: CurveGroup<ScalarField = F>, CS: CommitmentScheme<C>>(
+        &self,
+        cs_params: &CS::Params,
+    ) -> Result<Vec<CS::Commitment>, CCSError> {
+        self.M
+            .iter()
+            .map(|M_j| {
+                let mut evals = vec![F::zero(); 1 << (self.s + self.s_prime)];
+                for (row, entries) in M_j.coeffs.iter().enumerate() {
+                    for (value, col) in entries {
+                        evals[(row << self.s_prime) + col] += value;
+                    }
+                }
+                Ok(CS::commit(cs_params, &evals, &F::zero())?)
+            })
+            .collect()
+    }
+
+    /// Split a full assignment (x, w) of the CCS, which is z without its leading 1, into the public
+    /// io x and the witness w, sampling the randomness r_w of the witness commitment. Return an
+    /// error if z = (1, x, w) is not of length n or does not satisfy the CCS.
+    pub fn witness_from_assignment<R: Rng>(
         &self,
         rng: &mut R,
-        pedersen_params: &PedersenParams<C>,
-        z: &[C::ScalarField],
-    ) -> (CCCS<C>, Witness<C::ScalarField>) {
-        let w: Vec<C::ScalarField> = z[(1 + self.l)..].to_vec();
-        let r_w = C::ScalarField::rand(rng);
-        let C = Pedersen::<C>::commit(pedersen_params, &w, &r_w);
+        assignment: &[F],
+    ) -> Result<(Vec<F>, Witness<F>), CCSError> {
+        if assignment.len() + 1 != self.n {
+            return Err(CCSError::InvalidWitnessLength {
+                expected: self.n,
+                got: assignment.len() + 1,
+            });
+        }
+        let (x, w) = assignment.split_at(self.l);
+        let z = self.assemble_z(F::one(), x, w)?;
+        self.check_relation(&z)?;
+
+        Ok((
+            x.to_vec(),
+            Witness {
+                w: w.to_vec(),
+                r_w: F::rand(rng),
+            },
+        ))
+    }
 
-        (
-            CCCS::<C> {
-                ccs: self.clone(),
+    /// Commit to the witness part of z = (1, x, w), returning the CCCS instance and its witness.
+    /// Return `CCSError::InvalidWitnessLength` if z is not of length n.
+    pub fn to_cccs<R: Rng, C: CurveGroup<ScalarField = F>, P: CommitmentParams<Curve = C>>(
+        &self,
+        rng: &mut R,
+        cs_params: &P,
+        z: &[F],
+    ) -> Result<(CCCS<C, P::Scheme>, Witness<F>), CCSError> {
+        self.to_cccs_with_randomness(cs_params, z, F::rand(rng))
+    }
+
+    /// Same as [`Self::to_cccs`], with the randomness r_w of the commitment given by the caller
+    /// instead of sampled
+    pub fn to_cccs_with_randomness<
+        C: CurveGroup<ScalarField = F>,
+        P: CommitmentParams<Curve = C>,
+    >(
+        &self,
+        cs_params: &P,
+        z: &[F],
+        r_w: F,
+    ) -> Result<(CCCS<C, P::Scheme>, Witness<F>), CCSError> {
+        self.check_z_length(z)?;
+
+        let w: Vec<F> = z[(1 + self.l)..].to_vec();
+        let C = <P::Scheme as CommitmentScheme<C>>::commit(cs_params, &w, &r_w)?;
+
+        Ok((
+            CCCS::<C, P::Scheme> {
+                ccs: Arc::new(self.clone()),
                 C,
                 x: z[1..(1 + self.l)].to_vec(),
             },
-            Witness::<C::ScalarField> { w, r_w },
-        )
+            Witness::<F> { w, r_w },
+        ))
     }
 }
 
-impl<C: CurveGroup> CCCS<C> {
+impl<C: CurveGroup, CS: CommitmentScheme<C>> CCCS<C, CS> {
+    /// The `Debug` output of the whole instance, including the matrices of its CCS
+    pub fn debug_verbose(&self) -> String {
+        format!(
+            "CCCS {{ ccs: {:?}, C: {:?}, x: {:?} }}",
+            self.ccs, self.C, self.x
+        )
+    }
+
+    /// Return the z = (1, x, w) vector of the instance with the given witness
+    pub fn z(&self, w: &Witness<C::ScalarField>) -> Result<Vec<C::ScalarField>, CCSError> {
+        self.ccs.assemble_z(C::ScalarField::one(), &self.x, &w.w)
+    }
+
     /// Computes q(x) = \sum^q c_i * \prod_{j \in S_i} ( \sum_{y \in {0,1}^s'} M_j(x, y) * z(y) )
     /// polynomial over x
     pub fn compute_q(&self, z: &Vec<C::ScalarField>) -> VirtualPolynomial<C::ScalarField> {
-        let z_mle = vec_to_mle(self.ccs.s_prime, z);
-        let mut q = VirtualPolynomial::<C::ScalarField>::new(self.ccs.s);
-
-        for i in 0..self.ccs.q {
-            let mut prod: VirtualPolynomial<C::ScalarField> =
-                VirtualPolynomial::<C::ScalarField>::new(self.ccs.s);
-            for j in self.ccs.S[i].clone() {
-                let M_j = matrix_to_mle(self.ccs.M[j].clone());
-
-                let sum_Mz = compute_sum_Mz(M_j, &z_mle, self.ccs.s_prime);
-
-                // Fold this sum into the running product
-                if prod.products.is_empty() {
-                    // If this is the first time we are adding something to this virtual polynomial, we need to
-                    // explicitly add the products using add_mle_list()
-                    // XXX is this true? improve API
-                    prod.add_mle_list([Arc::new(sum_Mz)], C::ScalarField::one())
-                        .unwrap();
-                } else {
-                    prod.mul_by_mle(Arc::new(sum_Mz), C::ScalarField::one())
-                        .unwrap();
-                }
-            }
-            // Multiply by the product by the coefficient c_i
-            prod.scalar_mul(&self.ccs.c[i]);
-            // Add it to the running sum
-            q = q.add(&prod);
-        }
+        let q = self.ccs.q_from_plan(&self.ccs.prepare_q(), z);
+        debug_assert_eq!(q.aux_info.max_degree, self.ccs.degree());
         q
     }
 
@@ -104,27 +252,135 @@ impl<C: CurveGroup> CCCS<C> {
         q.build_f_hat(beta).unwrap()
     }
 
+    /// Linearize the CCCS instance into an LCCCS instance of the same witness, proving it with the
+    /// zero-check sumcheck of \sum_{x \in {0,1}^s} eq(beta, x) * q(x) = 0
+    ///
+    /// Unlike [`CCS::to_lcccs`], whose r_x is sampled by the prover and comes with no proof, beta
+    /// and r_x are derived from the transcript, and r_x is the point the sumcheck ends at, so the
+    /// verifier checks the v_j values with [`Self::verify_linearization`]. The LCCCS has u = 1,
+    /// so the witness is the one of the CCCS.
+    pub fn linearize(
+        &self,
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        w: &Witness<C::ScalarField>,
+    ) -> Result<
+        (
+            LCCCS<C, CS>,
+            Witness<C::ScalarField>,
+            SumCheckProof<C::ScalarField>,
+        ),
+        CCSError,
+    > {
+        let z = self.z(w)?;
+        let beta = self.absorb_linearization(transcript);
+
+        let Q = self.compute_Q(&z, &beta);
+        let proof = <PolyIOP<C::ScalarField> as SumCheck<C::ScalarField>>::prove(&Q, transcript)?;
+        let r_x = proof.point.clone();
+        let v = self.ccs.compute_v_j(&z, &r_x)?;
+        transcript.append_serializable_element(b"v", &v).unwrap();
+
+        Ok((self.to_lcccs_at(r_x, v), w.clone(), proof))
+    }
+
+    /// Verify the linearization of the CCCS instance into the LCCCS instance of the v_j values,
+    /// returning the LCCCS instance. The transcript must be in the same state as the one given to
+    /// [`Self::linearize`].
+    ///
+    /// The sumcheck reduces the claim to Q(r_x) = eq(beta, r_x) * q(r_x), and q(r_x) is computed
+    /// out of the v_j values as \sum c_i * \prod_{j \in S_i} v_j.
+    pub fn verify_linearization(
+        &self,
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        v: &[C::ScalarField],
+        proof: &SumCheckProof<C::ScalarField>,
+    ) -> Result<LCCCS<C, CS>, CCSError> {
+        if v.len() != self.ccs.t {
+            return Err(CCSError::VLengthMismatch {
+                t: self.ccs.t,
+                got: v.len(),
+            });
+        }
+        let beta = self.absorb_linearization(transcript);
+
+        let vp_aux_info = VPAuxInfo::<C::ScalarField> {
+            max_degree: self.ccs.d + 1,
+            num_variables: self.ccs.s,
+            phantom: PhantomData::<C::ScalarField>,
+        };
+        let subclaim = <PolyIOP<C::ScalarField> as SumCheck<C::ScalarField>>::verify(
+            C::ScalarField::zero(),
+            proof,
+            &vp_aux_info,
+            transcript,
+        )?;
+        let r_x = subclaim.point;
+
+        let q_r_x: C::ScalarField = self
+            .ccs
+            .S
+            .iter()
+            .zip(self.ccs.c.iter())
+            .map(|(S_i, c_i)| *c_i * S_i.iter().map(|j| v[*j]).product::<C::ScalarField>())
+            .sum();
+        if eq_eval(&beta, &r_x).unwrap() * q_r_x != subclaim.expected_evaluation {
+            return Err(CCSError::LinearizationClaimMismatch);
+        }
+        let v = v.to_vec();
+        transcript.append_serializable_element(b"v", &v).unwrap();
+
+        Ok(self.to_lcccs_at(r_x, v))
+    }
+
+    /// Absorb the instance into the transcript, returning the beta challenge of the zero-check
+    fn absorb_linearization(
+        &self,
+        transcript: &mut IOPTranscript<C::ScalarField>,
+    ) -> Vec<C::ScalarField> {
+        self.ccs.absorb_into(transcript);
+        transcript
+            .append_serializable_element(b"cccs C", &self.C)
+            .unwrap();
+        transcript
+            .append_serializable_element(b"cccs x", &self.x)
+            .unwrap();
+        transcript
+            .get_and_append_challenge_vectors(b"beta", self.ccs.s)
+            .unwrap()
+    }
+
+    /// The LCCCS instance of the same commitment and public io, with u = 1 and the v_j values at
+    /// r_x
+    fn to_lcccs_at(&self, r_x: Vec<C::ScalarField>, v: Vec<C::ScalarField>) -> LCCCS<C, CS> {
+        LCCCS {
+            ccs: self.ccs.clone(),
+            C: self.C.clone(),
+            u: C::ScalarField::one(),
+            x: self.x.clone(),
+            r_x,
+            v,
+        }
+    }
+
     /// Perform the check of the CCCS instance described at section 4.1
     pub fn check_relation(
         &self,
-        pedersen_params: &PedersenParams<C>,
+        cs_params: &CS::Params,
         w: &Witness<C::ScalarField>,
     ) -> Result<(), CCSError> {
-        // check that C is the commitment of w. Notice that this is not verifying a Pedersen
+        // check that C is the commitment of w. Notice that this is not verifying a commitment
         // opening, but checking that the Commmitment comes from committing to the witness.
-        assert_eq!(self.C.0, Pedersen::commit(pedersen_params, &w.w, &w.r_w).0);
+        if self.C != CS::commit(cs_params, &w.w, &w.r_w)? {
+            return Err(CCSError::CommitmentMismatch);
+        }
 
         // check CCCS relation
-        let z: Vec<C::ScalarField> =
-            [vec![C::ScalarField::one()], self.x.clone(), w.w.to_vec()].concat();
-
-        // A CCCS relation is satisfied if the q(x) multivariate polynomial evaluates to zero in the hypercube
-        let q_x = self.compute_q(&z);
-        for x in BooleanHypercube::new(self.ccs.s) {
-            if !q_x.evaluate(&x).unwrap().is_zero() {
-                return Err(CCSError::NotSatisfied);
-            }
-        }
+        let z = self.z(w)?;
+
+        // A CCCS relation is satisfied if the q(x) multivariate polynomial evaluates to zero in the
+        // hypercube. The evaluations of q(x) over the hypercube are the entries of the
+        // \sum c_i * \prod_{j \in S_i} M_j z vector, so check them directly instead of building q(x).
+        self.ccs.check_relation(&z)?;
 
         Ok(())
     }
@@ -133,9 +389,16 @@ impl<C: CurveGroup> CCCS<C> {
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use crate::ccs::ccs::test::{get_test_ccs, get_test_z};
+    use crate::ccs::ccs::test::{
+        get_test_ccs, get_test_ccs_cubic, get_test_ccs_signed, get_test_z, get_test_z_cubic,
+        get_test_z_signed,
+    };
+    use crate::util::hypercube::BooleanHypercube;
+    use crate::util::mle::matrix_to_mle;
+    use ark_poly::MultilinearExtension;
     use ark_std::test_rng;
     use ark_std::UniformRand;
+    use ark_std::Zero;
 
     use ark_bls12_381::{Fr, G1Projective};
 
@@ -145,11 +408,12 @@ pub mod test {
     fn test_compute_q() -> () {
         let mut rng = test_rng();
 
-        let ccs = get_test_ccs::<G1Projective>();
+        let ccs = get_test_ccs::<Fr>();
         let z = get_test_z(3);
 
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
         let q = cccs.compute_q(&z);
 
         // Evaluate inside the hypercube
@@ -162,17 +426,61 @@ pub mod test {
         assert_ne!(Fr::zero(), q.evaluate(&beta).unwrap());
     }
 
+    /// Check that q(x) has the degree of the CCS and vanishes in the hypercube for a CCS with a cubic
+    /// custom gate
+    #[test]
+    fn test_compute_q_cubic() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs_cubic::<Fr>();
+        let z = get_test_z_cubic(3);
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+        let q = cccs.compute_q(&z);
+        assert_eq!(q.aux_info.max_degree, 3);
+
+        for x in BooleanHypercube::new(ccs.s) {
+            assert_eq!(Fr::zero(), q.evaluate(&x).unwrap());
+        }
+        cccs.check_relation(&pedersen_params, &w).unwrap();
+    }
+
+    /// q(x) vanishes in the hypercube for a CCS whose gate only holds thanks to its negative
+    /// constants
+    #[test]
+    fn test_compute_q_signed() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs_signed::<Fr>();
+        let z = get_test_z_signed(5, 3);
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+        let q = cccs.compute_q(&z);
+
+        for x in BooleanHypercube::new(ccs.s) {
+            assert_eq!(Fr::zero(), q.evaluate(&x).unwrap());
+        }
+        let beta: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        assert_ne!(Fr::zero(), q.evaluate(&beta).unwrap());
+        cccs.check_relation(&pedersen_params, &w).unwrap();
+    }
+
     /// Perform some sanity checks on Q(x).
     #[test]
     fn test_compute_Q() -> () {
         let mut rng = test_rng();
 
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z = get_test_z(3);
         ccs.check_relation(&z).unwrap();
 
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
 
         let beta: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
 
@@ -205,12 +513,13 @@ pub mod test {
     fn test_Q_against_q() -> () {
         let mut rng = test_rng();
 
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z = get_test_z(3);
         ccs.check_relation(&z).unwrap();
 
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
 
         // Now test that if we create Q(x) with eq(d,y) where d is inside the hypercube, \sum Q(x) should be G(d) which
         // should be equal to q(d), since G(x) interpolates q(x) inside the hypercube
@@ -237,4 +546,336 @@ pub mod test {
             .fold(Fr::zero(), |acc, result| acc + result);
         assert_ne!(G_at_r, q.evaluate(&r).unwrap());
     }
+
+    /// Check that the matrix-based check used in check_relation agrees with evaluating q(x) over
+    /// the boolean hypercube
+    #[test]
+    fn test_check_relation_against_q() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let z = get_test_z(3);
+        let mut bad_z = z.clone();
+        bad_z[3] += Fr::one();
+
+        for z in [z, bad_z] {
+            let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+            let q = cccs.compute_q(&z);
+            let q_vanishes =
+                BooleanHypercube::new(ccs.s).all(|x| q.evaluate(&x).unwrap().is_zero());
+            assert_eq!(
+                cccs.check_relation(&pedersen_params, &w).is_ok(),
+                q_vanishes
+            );
+        }
+    }
+
+    /// Check that a plan prepared once builds the right q(x) for many z vectors, by evaluating it
+    /// against \sum_i c_i * \prod_{j \in S_i} (\sum_y M_j(r, y) * z(y)) at a random point
+    #[test]
+    fn test_q_from_plan() -> () {
+        let mut rng = test_rng();
+
+        for ccs in [get_test_ccs::<Fr>(), get_test_ccs_cubic::<Fr>()] {
+            let plan = ccs.prepare_q();
+            for i in 3..6 {
+                let z = [
+                    vec![Fr::one()],
+                    (1..ccs.n).map(|_| Fr::rand(&mut rng)).collect(),
+                ]
+                .concat();
+                let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+                let mut expected = Fr::zero();
+                for (S_i, c_i) in ccs.S.iter().zip(ccs.c.iter()) {
+                    let mut prod = *c_i;
+                    for j in S_i {
+                        prod *= compute_sum_Mz(&ccs.M[*j], &z, ccs.s).evaluate(&r).unwrap();
+                    }
+                    expected += prod;
+                }
+
+                let q = ccs.q_from_plan(&plan, &z);
+                assert_eq!(q.evaluate(&r).unwrap(), expected, "z #{}", i);
+                assert_eq!(q.aux_info.max_degree, ccs.degree());
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_cccs_invalid_z_length() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z: Vec<Fr> = get_test_z(3);
+
+        let too_short = z[..ccs.n - 1].to_vec();
+        let too_long = [z, vec![Fr::one()]].concat();
+        for bad_z in [too_short, too_long] {
+            let result = ccs.to_cccs(&mut rng, &pedersen_params, &bad_z);
+            assert!(matches!(
+                result,
+                Err(CCSError::InvalidWitnessLength { expected, got })
+                    if expected == ccs.n && got == bad_z.len()
+            ));
+        }
+    }
+
+    /// A CCCS instance with a non-hiding commitment is opened by a witness with r_w = 0
+    #[test]
+    fn test_cccs_non_hiding() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z: Vec<Fr> = get_test_z(3);
+
+        let w = Witness::<Fr> {
+            w: z[(1 + ccs.l)..].to_vec(),
+            r_w: Fr::zero(),
+        };
+        let cccs = CCCS::<G1Projective> {
+            ccs: Arc::new(ccs.clone()),
+            C: Pedersen::<G1Projective>::commit_with_hiding(
+                &pedersen_params,
+                &w.w,
+                &Fr::rand(&mut rng),
+                false,
+            )
+            .unwrap(),
+            x: z[1..(1 + ccs.l)].to_vec(),
+        };
+        cccs.check_relation(&pedersen_params, &w).unwrap();
+    }
+
+    /// The matrix commitments are commitments to the MLE evaluation tables of the matrices, and
+    /// removing an entry (i, j) from the commitment opens it at the index of (i, j)
+    #[test]
+    fn test_commit_matrices() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, 1 << (ccs.s + ccs.s_prime));
+
+        let commitments = ccs
+            .commit_matrices::<G1Projective, Pedersen<G1Projective>>(&pedersen_params)
+            .unwrap();
+        assert_eq!(commitments.len(), ccs.t);
+        for (M_j, cm_j) in ccs.M.iter().zip(commitments.iter()) {
+            let M_j_mle = matrix_to_mle(M_j.to_dense());
+            assert_eq!(
+                cm_j,
+                &Pedersen::<G1Projective>::commit(
+                    &pedersen_params,
+                    &M_j_mle.evaluations,
+                    &Fr::zero()
+                )
+                .unwrap()
+            );
+        }
+
+        // A = M_0 has the entry 5 at (3, 0)
+        let (i, j) = (3, 0);
+        let index = i * (1 << ccs.s_prime) + j;
+        let value = ccs.M[0].to_dense()[i][j];
+        assert_eq!(value, Fr::from(5u64));
+        let x = BooleanHypercube::new(ccs.s).at_i(i);
+        let y = BooleanHypercube::new(ccs.s_prime).at_i(j);
+        assert_eq!(
+            matrix_to_mle(ccs.M[0].to_dense())
+                .evaluate(&[y, x].concat())
+                .unwrap(),
+            value
+        );
+
+        let mut other_ccs = ccs.clone();
+        other_ccs.M[0].coeffs[i].retain(|(_, col)| *col != j);
+        let other_commitments = other_ccs
+            .commit_matrices::<G1Projective, Pedersen<G1Projective>>(&pedersen_params)
+            .unwrap();
+        assert_eq!(
+            commitments[0].0 - pedersen_params.generators[index] * value,
+            other_commitments[0].0
+        );
+        assert_eq!(commitments[1..], other_commitments[1..]);
+
+        // the parameters must cover the whole evaluation table
+        assert!(ccs
+            .commit_matrices::<G1Projective, Pedersen<G1Projective>>(
+                &Pedersen::<G1Projective>::new_params(&mut rng, ccs.n)
+            )
+            .is_err());
+    }
+
+    /// Linearizing a CCCS gives a satisfied LCCCS, which the verifier accepts, while the proof of
+    /// the linearization of an unsatisfied CCCS or with tampered v_j values is rejected
+    #[test]
+    fn test_cccs_linearize() -> () {
+        let mut rng = test_rng();
+
+        for (ccs, z) in [
+            (get_test_ccs::<Fr>(), get_test_z(3)),
+            (get_test_ccs_cubic::<Fr>(), get_test_z_cubic(3)),
+        ] {
+            let pedersen_params =
+                Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+            let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+
+            let mut transcript_p = IOPTranscript::<Fr>::new(b"linearization");
+            let (lcccs, w_lcccs, proof) = cccs.linearize(&mut transcript_p, &w).unwrap();
+            assert_eq!(lcccs.r_x, proof.point);
+            assert!(lcccs.u.is_one());
+            lcccs.check_relation(&pedersen_params, &w_lcccs).unwrap();
+
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"linearization");
+            let lcccs_v = cccs
+                .verify_linearization(&mut transcript_v, &lcccs.v, &proof)
+                .unwrap();
+            assert_eq!(lcccs_v, lcccs);
+            // both transcripts end in the same state
+            assert_eq!(
+                transcript_p.get_and_append_challenge(b"next").unwrap(),
+                transcript_v.get_and_append_challenge(b"next").unwrap()
+            );
+
+            let mut bad_v = lcccs.v.clone();
+            bad_v[0] += Fr::one();
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"linearization");
+            assert!(matches!(
+                cccs.verify_linearization(&mut transcript_v, &bad_v, &proof),
+                Err(CCSError::LinearizationClaimMismatch)
+            ));
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"linearization");
+            assert!(matches!(
+                cccs.verify_linearization(&mut transcript_v, &bad_v[1..], &proof),
+                Err(CCSError::VLengthMismatch { .. })
+            ));
+
+            // the sum of eq(beta, x) * q(x) over the hypercube is not zero for an unsatisfied z,
+            // so the sumcheck proof is rejected
+            let mut bad_w = w.clone();
+            bad_w.w[0] += Fr::one();
+            let mut transcript_p = IOPTranscript::<Fr>::new(b"linearization");
+            let (bad_lcccs, _, bad_proof) = cccs.linearize(&mut transcript_p, &bad_w).unwrap();
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"linearization");
+            assert!(matches!(
+                cccs.verify_linearization(&mut transcript_v, &bad_lcccs.v, &bad_proof),
+                Err(CCSError::SumCheck(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_witness_from_assignment() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let z: Vec<Fr> = get_test_z(3);
+
+        let (x, w) = ccs.witness_from_assignment(&mut rng, &z[1..]).unwrap();
+        assert_eq!(x, z[1..2].to_vec());
+        assert_eq!(w.w, z[2..].to_vec());
+
+        // the witness opens a commitment to w, as in a CCCS instance
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let cccs = CCCS::<G1Projective> {
+            ccs: Arc::new(ccs.clone()),
+            C: Pedersen::<G1Projective>::commit(&pedersen_params, &w.w, &w.r_w).unwrap(),
+            x,
+        };
+        cccs.check_relation(&pedersen_params, &w).unwrap();
+
+        let mut bad_assignment = z[1..].to_vec();
+        bad_assignment[1] += Fr::one();
+        assert!(matches!(
+            ccs.witness_from_assignment(&mut rng, &bad_assignment),
+            Err(CCSError::NotSatisfied { .. })
+        ));
+        assert!(matches!(
+            ccs.witness_from_assignment(&mut rng, &z),
+            Err(CCSError::InvalidWitnessLength {
+                expected: 6,
+                got: 7
+            })
+        ));
+    }
+
+    /// The CCCS is printed with the dimensions of its CCS instead of its matrices
+    #[test]
+    fn test_cccs_display() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, _) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+
+        assert_eq!(
+            cccs.to_string(),
+            format!(
+                "CCCS of a CCS with m = 4, n = 6, l = 1, t = 3, q = 2, d = 2: C = {:?}, |x| = 1",
+                cccs.C
+            )
+        );
+        let debug = format!("{:?}", cccs);
+        assert!(!debug.contains("coeffs"));
+        assert!(cccs.debug_verbose().contains("coeffs"));
+        assert!(cccs
+            .debug_verbose()
+            .starts_with("CCCS { ccs: CCS { m: 4, n: 6, l: 1"));
+    }
+
+    /// The z vector of a CCCS starts with 1, like the z vector of an LCCCS with u = 1
+    #[test]
+    fn test_cccs_z() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z: Vec<Fr> = get_test_z(3);
+
+        let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+        assert_eq!(cccs.z(&w).unwrap(), z);
+
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+        assert_eq!(lcccs.u, Fr::one());
+        assert_eq!(lcccs.z(&w).unwrap(), cccs.z(&w).unwrap());
+
+        let bad_w = Witness::<Fr> {
+            w: w.w[1..].to_vec(),
+            r_w: w.r_w,
+        };
+        assert!(matches!(
+            cccs.z(&bad_w),
+            Err(CCSError::InvalidWLength {
+                expected: 4,
+                got: 3
+            })
+        ));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_witness_zeroize() -> () {
+        use zeroize::Zeroize;
+
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (_, mut w) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        assert!(!w.w.is_empty());
+
+        w.zeroize();
+        // the w vector gets wiped and truncated, and the randomness set to zero
+        assert!(w.w.is_empty());
+        assert!(w.r_w.is_zero());
+    }
 }
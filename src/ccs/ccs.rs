@@ -1,20 +1,224 @@
-use ark_ec::CurveGroup;
-use ark_std::{One, Zero};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{cfg_iter, One, Zero};
+use core::fmt;
+use core::ops::Neg;
+use sha2::{Digest, Sha256};
+use subroutines::poly_iop::prelude::PolyIOPErrors;
+use transcript::IOPTranscript;
+
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 // XXX use thiserror everywhere? espresso doesnt use it...
 use thiserror::Error;
 
+use crate::ccs::commitment::CommitmentError;
+use crate::util::math::log2_ceil;
 use crate::util::vec::*;
 
 #[derive(Error, Debug)]
 pub enum CCSError {
-    #[error("Relation not satisfied")]
-    NotSatisfied,
+    /// The constraint of the given row, which is the point of the boolean hypercube {0,1}^s given
+    /// by the little-endian bits of the row, evaluates to the nonzero value instead of zero
+    #[error("Relation not satisfied at row {row}, the hypercube point {point:?}, where it evaluates to {value}")]
+    NotSatisfied {
+        row: usize,
+        point: Vec<u8>,
+        value: String,
+    },
+    #[error("Commitment does not match the witness")]
+    CommitmentMismatch,
+    #[error("v_{index} does not match the evaluation computed from the witness")]
+    VEvaluationMismatch { index: usize },
+    #[error("The random linear combination of the v_j values does not match the witness")]
+    VCombinationMismatch,
+    #[error("Expected t = {t} v values, got {got}")]
+    VLengthMismatch { t: usize, got: usize },
+    #[error("Expected t = {t} evaluations in each sigma and theta, got {got}")]
+    SigmaThetaLengthMismatch { t: usize, got: usize },
+    #[error("Row {row} is out of the m = {m} constraints")]
+    RowOutOfRange { row: usize, m: usize },
+    #[error("{n} is not a power of two")]
+    NotPowerOfTwo { n: usize },
+    #[error("The dimension {n} can not be padded to a power of two")]
+    DimensionTooLarge { n: usize },
+    #[error("s = {s} does not match log2(m) for m = {m}")]
+    InvalidS { m: usize, s: usize },
+    #[error("s' = {s_prime} does not match log2(n) for n = {n}")]
+    InvalidSPrime { n: usize, s_prime: usize },
+    #[error("l = {l} does not fit in a z vector of length n = {n}")]
+    InvalidIOLength { n: usize, l: usize },
+    #[error("The MLE tables of 2^(s + s') evaluations overflow for s = {s} and s' = {s_prime}")]
+    MleTablesTooLarge { s: usize, s_prime: usize },
+    #[error("MLE table {index} has {got} evaluations instead of 2^(s + s') = {expected}")]
+    InvalidMleTableLength {
+        index: usize,
+        expected: usize,
+        got: usize,
+    },
+    #[error("The witness is all zeros")]
+    ZeroWitness,
+    #[error("A z vector of length n = {n} with l = {l} public inputs leaves no witness entries")]
+    EmptyWitness { n: usize, l: usize },
+    #[error("Expected t = {t} matrices, got {got}")]
+    InvalidNumberOfMatrices { t: usize, got: usize },
+    #[error("Matrix {index} is {rows}x{cols}, expected {m}x{n}")]
+    InvalidMatrixDimensions {
+        index: usize,
+        rows: usize,
+        cols: usize,
+        m: usize,
+        n: usize,
+    },
+    #[error("Matrix {index} declares {n_rows} rows, but has coefficients for {got} rows")]
+    InvalidMatrixRows {
+        index: usize,
+        n_rows: usize,
+        got: usize,
+    },
+    #[error("Matrix {index} has an entry at column {col}, out of range for n = {n}")]
+    MatrixEntryOutOfRange { index: usize, col: usize, n: usize },
+    #[error("Expected q = {q} multisets, got {got}")]
+    InvalidNumberOfMultisets { q: usize, got: usize },
+    #[error("Expected q = {q} coefficients, got {got}")]
+    InvalidNumberOfCoefficients { q: usize, got: usize },
+    #[error("Multiset {set} contains the matrix index {index}, out of range for t = {t}")]
+    MatrixIndexOutOfRange { set: usize, index: usize, t: usize },
+    #[error("Multiset {set} has degree {degree}, larger than d = {d}")]
+    DegreeTooLarge { set: usize, degree: usize, d: usize },
+    #[error("Expected a z vector of length {expected}, got {got}")]
+    InvalidWitnessLength { expected: usize, got: usize },
+    #[error("Expected a public input/output of length {expected}, got {got}")]
+    InvalidPublicInputLength { expected: usize, got: usize },
+    #[error("Expected an evaluation point of length {expected}, got {got}")]
+    InvalidChallengeLength { expected: usize, got: usize },
+    #[error("Expected a w vector of length {expected}, got {got}")]
+    InvalidWLength { expected: usize, got: usize },
+    #[error("Expected the wiring of {gates} gates, got {got}")]
+    InvalidWiringLength { gates: usize, got: usize },
+    #[error("At least one LCCCS instance is needed to fold")]
+    NoLCCCSInstances,
+    #[error("Expected mu = {mu} sigmas, got {got}")]
+    InvalidNumberOfSigmas { mu: usize, got: usize },
+    #[error("Expected nu = {nu} thetas, got {got}")]
+    InvalidNumberOfThetas { nu: usize, got: usize },
+    #[error("The relaxation factor u of the LCCCS instance is not 1")]
+    InvalidRelaxationFactor,
+    #[error("The instances do not share the same CCS structure")]
+    IncompatibleCCS,
+    #[error("Commitment error: {0}")]
+    Commitment(#[from] CommitmentError),
+    #[error("Deserialization error: {0}")]
+    Deserialization(#[from] SerializationError),
+    #[error("{got} bytes left after deserializing the instance")]
+    TrailingBytes { got: usize },
+    #[error("There is no segment {name} in the public io layout")]
+    UnknownIoSegment { name: String },
+    #[error("The segment {name} appears twice in the public io layout")]
+    DuplicateIoSegment { name: String },
+    #[error("Sumcheck error: {0}")]
+    SumCheck(PolyIOPErrors),
+    #[error("Sumcheck claim does not match the value computed from the v_j values")]
+    LinearizationClaimMismatch,
 }
 
-/// A CCS structure
+impl From<PolyIOPErrors> for CCSError {
+    fn from(e: PolyIOPErrors) -> Self {
+        CCSError::SumCheck(e)
+    }
+}
+
+/// A Plonkish gate q_L * a + q_R * b + q_O * c + q_M * a * b + q_C = 0, where a, b and c are the
+/// entries of z wired to the gate
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlonkishGate<F: PrimeField> {
+    pub q_L: F,
+    pub q_R: F,
+    pub q_O: F,
+    pub q_M: F,
+    pub q_C: F,
+}
+
+/// Sizes of a CCS structure and of the work it takes to fold its instances, see [`CCS::stats`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CcsStats {
+    pub m: usize,
+    pub n: usize,
+    pub l: usize,
+    pub t: usize,
+    pub q: usize,
+    pub d: usize,
+    pub s: usize,
+    pub s_prime: usize,
+    /// Number of nonzero entries of each matrix, which is the cost of its M_j z product
+    pub nonzeros: Vec<usize>,
+    /// Number of MLE products evaluated to sum q(x) over the 2^s points of the hypercube, that
+    /// is 2^s * \sum_i |S_i|
+    pub hypercube_work: usize,
+}
+
+impl fmt::Display for CcsStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "CCS of m = {} constraints (s = {}) and n = {} variables (s' = {}), l = {}",
+            self.m, self.s, self.n, self.s_prime, self.l
+        )?;
+        writeln!(
+            f,
+            "t = {} matrices, q = {} multisets of degree d = {}",
+            self.t, self.q, self.d
+        )?;
+        let total: usize = self.nonzeros.iter().sum();
+        let density = total as f64 / (self.t * self.m * self.n).max(1) as f64;
+        writeln!(
+            f,
+            "nonzeros: {:?}, {} in total ({:.2}% dense)",
+            self.nonzeros,
+            total,
+            100.0 * density
+        )?;
+        write!(f, "hypercube work: {} products", self.hypercube_work)
+    }
+}
+
+/// Estimated cost of folding one LCCCS and one CCCS instance of a CCS, see [`CCS::cost_model`]
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct CCS<C: CurveGroup> {
+pub struct CostModel {
+    /// Number of sumcheck rounds, one per variable of the hypercube of the m constraints
+    pub sumcheck_rounds: usize,
+    /// Degree of the univariate polynomial sent in each round, d + 1 since g(x) multiplies the
+    /// degree d multisets by an eq polynomial
+    pub round_degree: usize,
+    /// Number of field elements of the proof: the round_degree + 1 evaluations of each round, and
+    /// the t sigmas and t thetas
+    pub proof_size: usize,
+    /// Number of group operations on the commitments: the C_1 + C_2 * rho of the fold is one
+    /// scalar multiplication and one addition
+    pub commitment_group_ops: usize,
+    /// Rough count of the field multiplications of the verifier: evaluating each round
+    /// polynomial, the two eq polynomials of the claimed evaluation, combining the sigmas and
+    /// thetas into it, and folding u, x and v
+    pub verifier_field_ops: usize,
+}
+
+impl fmt::Display for CostModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "sumcheck: {} rounds of degree {}",
+            self.sumcheck_rounds, self.round_degree
+        )?;
+        writeln!(f, "proof size: {} field elements", self.proof_size)?;
+        writeln!(f, "commitment group ops: {}", self.commitment_group_ops)?;
+        write!(f, "verifier field ops: {}", self.verifier_field_ops)
+    }
+}
+
+/// A CCS structure
+#[derive(Debug, Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CCS<F: PrimeField> {
     // m: number of columns in M_i (such that M_i \in F^{m, n})
     pub m: usize,
     // n = |z|, number of rows in M_i
@@ -33,88 +237,630 @@ pub struct CCS<C: CurveGroup> {
     pub s_prime: usize,
 
     // Vector of matrices
-    pub M: Vec<Matrix<C::ScalarField>>,
+    pub M: Vec<SparseMatrix<F>>,
     // Vector of multisets
     pub S: Vec<Vec<usize>>,
     // Vector of coefficients
-    pub c: Vec<C::ScalarField>,
+    pub c: Vec<F>,
 }
 
-impl<C: CurveGroup> CCS<C> {
-    /// Check that a CCS structure is satisfied by a z vector.
-    /// This works with matrices. It doesn't do any polynomial stuff
-    /// Only for testing
-    pub fn check_relation(&self, z: &[C::ScalarField]) -> Result<(), CCSError> {
-        let mut result = vec![C::ScalarField::zero(); self.m];
-
-        for i in 0..self.q {
-            // XXX This can be done more neatly with a .fold() or .reduce()
+impl<F: PrimeField> CCS<F> {
+    /// Build a CCS structure out of its matrices, multisets and coefficients, deriving the rest of
+    /// the parameters from them and checking that the structure is well formed.
+    pub fn new(
+        M: Vec<SparseMatrix<F>>,
+        S: Vec<Vec<usize>>,
+        c: Vec<F>,
+        l: usize,
+    ) -> Result<Self, CCSError> {
+        if M.is_empty() {
+            return Err(CCSError::InvalidNumberOfMatrices { t: 1, got: 0 });
+        }
+        let m = M[0].n_rows;
+        let n = M[0].n_cols;
+        // the dimensions don't need to be powers of two, the MLEs of the matrices and z vectors are
+        // padded with zeros, so s and s' are the number of variables of the padded MLEs
+        let ccs = CCS {
+            m,
+            n,
+            l,
+            t: M.len(),
+            q: S.len(),
+            d: Self::multisets_degree(&S),
+            s: log2_ceil(m)?,
+            s_prime: log2_ceil(n)?,
+            M,
+            S,
+            c,
+        };
+        ccs.check()?;
+        Ok(ccs)
+    }
 
-            // Extract the needed M_j matrices out of S_i
-            let vec_M_j: Vec<&Matrix<C::ScalarField>> =
-                self.S[i].iter().map(|j| &self.M[*j]).collect();
+    /// Return the degree of the CCS, which is the cardinality of its largest multiset
+    pub fn degree(&self) -> usize {
+        Self::multisets_degree(&self.S)
+    }
 
-            // Complete the hadamard chain
-            let mut hadamard_result = vec![C::ScalarField::one(); self.m];
-            for M_j in vec_M_j.into_iter() {
-                hadamard_result = hadamard(&hadamard_result, &mat_vec_mul(M_j, z));
-            }
+    fn multisets_degree(S: &[Vec<usize>]) -> usize {
+        S.iter().map(|S_i| S_i.len()).max().unwrap_or(0)
+    }
 
-            // Multiply by the coefficient of this step
-            let c_M_j_z = vec_scalar_mul(&hadamard_result, &self.c[i]);
+    /// Check the structural invariants of the CCS: the dimensions of the matrices, the sizes of
+    /// the multisets and coefficients, and that the multisets only reference existing matrices.
+    pub fn check(&self) -> Result<(), CCSError> {
+        if self.s != log2_ceil(self.m)? {
+            return Err(CCSError::InvalidS {
+                m: self.m,
+                s: self.s,
+            });
+        }
+        if self.s_prime != log2_ceil(self.n)? {
+            return Err(CCSError::InvalidSPrime {
+                n: self.n,
+                s_prime: self.s_prime,
+            });
+        }
+        // z = (1, x, w), so the public io can't take the whole z vector
+        if self.l >= self.n {
+            return Err(CCSError::InvalidIOLength {
+                n: self.n,
+                l: self.l,
+            });
+        }
 
-            // Add it to the final vector
-            result = vec_add(&result, &c_M_j_z);
+        if self.M.len() != self.t {
+            return Err(CCSError::InvalidNumberOfMatrices {
+                t: self.t,
+                got: self.M.len(),
+            });
+        }
+        for (index, M_j) in self.M.iter().enumerate() {
+            if M_j.n_rows != self.m || M_j.n_cols != self.n {
+                return Err(CCSError::InvalidMatrixDimensions {
+                    index,
+                    rows: M_j.n_rows,
+                    cols: M_j.n_cols,
+                    m: self.m,
+                    n: self.n,
+                });
+            }
+            if M_j.coeffs.len() != M_j.n_rows {
+                return Err(CCSError::InvalidMatrixRows {
+                    index,
+                    n_rows: M_j.n_rows,
+                    got: M_j.coeffs.len(),
+                });
+            }
+            for &(_, col) in M_j.coeffs.iter().flatten() {
+                if col >= self.n {
+                    return Err(CCSError::MatrixEntryOutOfRange {
+                        index,
+                        col,
+                        n: self.n,
+                    });
+                }
+            }
         }
 
-        // Make sure the final vector is all zeroes
-        for e in result {
-            if !e.is_zero() {
-                return Err(CCSError::NotSatisfied);
+        if self.S.len() != self.q {
+            return Err(CCSError::InvalidNumberOfMultisets {
+                q: self.q,
+                got: self.S.len(),
+            });
+        }
+        if self.c.len() != self.q {
+            return Err(CCSError::InvalidNumberOfCoefficients {
+                q: self.q,
+                got: self.c.len(),
+            });
+        }
+        for (set, S_i) in self.S.iter().enumerate() {
+            if S_i.len() > self.d {
+                return Err(CCSError::DegreeTooLarge {
+                    set,
+                    degree: S_i.len(),
+                    d: self.d,
+                });
+            }
+            if let Some(&index) = S_i.iter().find(|&&j| j >= self.t) {
+                return Err(CCSError::MatrixIndexOutOfRange {
+                    set,
+                    index,
+                    t: self.t,
+                });
             }
         }
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-pub mod test {
-    use super::*;
-    use ark_bls12_381::G1Projective;
-    use ark_ff::PrimeField;
-    use ark_std::log2;
-    use std::ops::Neg;
-
-    /// Converts the R1CS structure to the CCS structure
-    fn CCS_from_r1cs<C: CurveGroup>(
-        A: Vec<Vec<C::ScalarField>>,
-        B: Vec<Vec<C::ScalarField>>,
-        C: Vec<Vec<C::ScalarField>>,
+    /// Converts the R1CS structure (A, B, C) to the CCS structure, such that the CCS relation
+    /// reduces to Az ∘ Bz - Cz = 0
+    pub fn from_r1cs(
+        A: SparseMatrix<F>,
+        B: SparseMatrix<F>,
+        C: SparseMatrix<F>,
         io_len: usize,
-    ) -> CCS<C> {
-        let m = A.len();
-        let n = A[0].len();
+    ) -> Result<Self, CCSError> {
+        Self::new(
+            vec![A, B, C],
+            vec![vec![0, 1], vec![2]],
+            vec![F::one(), F::one().neg()],
+            io_len,
+        )
+    }
+
+    /// Same as [`Self::new`], with the constants c given as signed integers, so that subtractive
+    /// terms such as the -Cz of R1CS can be written as -1 instead of p - 1. A negative c_i is
+    /// mapped to the field element -|c_i|.
+    pub fn with_signed_constants(
+        M: Vec<SparseMatrix<F>>,
+        S: Vec<Vec<usize>>,
+        c: &[i64],
+        l: usize,
+    ) -> Result<Self, CCSError> {
+        let c = c
+            .iter()
+            .map(|&c_i| {
+                if c_i < 0 {
+                    F::from(c_i.unsigned_abs()).neg()
+                } else {
+                    F::from(c_i as u64)
+                }
+            })
+            .collect();
+        Self::new(M, S, c, l)
+    }
+
+    /// Build a CCS of m = 2^s rows and n = 2^s' columns out of the evaluation tables of the MLEs
+    /// of its matrices, without going through dense matrices. The table of M_j has the 2^(s + s')
+    /// evaluations M_j(x, y) in the order of [`crate::util::mle::matrix_to_mle`], where the entry
+    /// (row, col) is at index (row << s') + col, and only its nonzero entries are kept.
+    ///
+    /// Return an error if 2^(s + s') overflows, if a table is not of length 2^(s + s'), or if the
+    /// CCS built out of the tables is invalid (see [`Self::new`]).
+    pub fn from_mle_tables(
+        tables: Vec<Vec<F>>,
+        s: usize,
+        s_prime: usize,
+        l: usize,
+        S: Vec<Vec<usize>>,
+        c: Vec<F>,
+    ) -> Result<Self, CCSError> {
+        if !s
+            .checked_add(s_prime)
+            .is_some_and(|vars| vars < usize::BITS as usize)
+        {
+            return Err(CCSError::MleTablesTooLarge { s, s_prime });
+        }
+        let (m, n) = (1 << s, 1 << s_prime);
+        if let Some((index, table)) = tables
+            .iter()
+            .enumerate()
+            .find(|(_, table)| table.len() != m * n)
+        {
+            return Err(CCSError::InvalidMleTableLength {
+                index,
+                expected: m * n,
+                got: table.len(),
+            });
+        }
+        let M = tables
+            .iter()
+            .map(|table| SparseMatrix {
+                n_rows: m,
+                n_cols: n,
+                coeffs: table
+                    .chunks(n)
+                    .map(|row| {
+                        row.iter()
+                            .enumerate()
+                            .filter(|(_, value)| !value.is_zero())
+                            .map(|(col, value)| (*value, col))
+                            .collect()
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self::new(M, S, c, l)
+    }
+
+    /// Build the CCS structure of a Plonkish constraint system, where the i-th gate is applied to
+    /// the entries of z = (1, io, w) at the indices `wiring[i] = [a, b, c]`. The length of z is
+    /// derived from the largest wired index.
+    ///
+    /// Each selector is folded into the matrix selecting its wire, so the gates become
+    /// M_L z + M_R z + M_O z + M_A z ∘ M_B z + M_C z = 0, with M_B holding q_M and M_C holding q_C
+    /// in the column of the constant 1.
+    pub fn from_plonkish(
+        gates: &[PlonkishGate<F>],
+        wiring: &[[usize; 3]],
+        io_len: usize,
+    ) -> Result<Self, CCSError> {
+        if wiring.len() != gates.len() {
+            return Err(CCSError::InvalidWiringLength {
+                gates: gates.len(),
+                got: wiring.len(),
+            });
+        }
+        let n = wiring
+            .iter()
+            .flatten()
+            .map(|i| i + 1)
+            .chain([io_len + 1])
+            .max()
+            .unwrap();
+
+        // build a matrix whose i-th row has the value f(gate_i) at the column col(wiring_i)
+        let matrix =
+            |f: &dyn Fn(&PlonkishGate<F>) -> F, col: &dyn Fn(&[usize; 3]) -> usize| SparseMatrix {
+                n_rows: gates.len(),
+                n_cols: n,
+                coeffs: gates
+                    .iter()
+                    .zip(wiring.iter())
+                    .map(|(gate, wires)| {
+                        let value = f(gate);
+                        if value.is_zero() {
+                            vec![]
+                        } else {
+                            vec![(value, col(wires))]
+                        }
+                    })
+                    .collect(),
+            };
+
+        let M_L = matrix(&|gate| gate.q_L, &|wires| wires[0]);
+        let M_R = matrix(&|gate| gate.q_R, &|wires| wires[1]);
+        let M_O = matrix(&|gate| gate.q_O, &|wires| wires[2]);
+        let M_A = matrix(&|_| F::one(), &|wires| wires[0]);
+        let M_B = matrix(&|gate| gate.q_M, &|wires| wires[1]);
+        let M_C = matrix(&|gate| gate.q_C, &|_| 0);
+
+        Self::new(
+            vec![M_L, M_R, M_O, M_A, M_B, M_C],
+            vec![vec![0], vec![1], vec![2], vec![3, 4], vec![5]],
+            vec![F::one(); 5],
+            io_len,
+        )
+    }
+
+    /// The dimensions of the CCS in a single line, to summarize the CCS of an instance without
+    /// printing its matrices
+    pub(crate) fn dimensions(&self) -> String {
+        format!(
+            "m = {}, n = {}, l = {}, t = {}, q = {}, d = {}",
+            self.m, self.n, self.l, self.t, self.q, self.d
+        )
+    }
+
+    /// Return the sizes of the CCS, to see how much work folding its instances takes and whether
+    /// [`Self::simplify`] or [`Self::pad_to_pow2`] change it
+    pub fn stats(&self) -> CcsStats {
+        CcsStats {
+            m: self.m,
+            n: self.n,
+            l: self.l,
+            t: self.t,
+            q: self.q,
+            d: self.d,
+            s: self.s,
+            s_prime: self.s_prime,
+            nonzeros: self
+                .M
+                .iter()
+                .map(|M_j| {
+                    M_j.coeffs
+                        .iter()
+                        .flatten()
+                        .filter(|(value, _)| !value.is_zero())
+                        .count()
+                })
+                .collect(),
+            hypercube_work: (1 << self.s) * self.S.iter().map(|S_i| S_i.len()).sum::<usize>(),
+        }
+    }
+
+    /// Estimate the cost of folding one LCCCS and one CCCS instance of the CCS, to compare circuit
+    /// formulations before writing them. It only depends on the parameters of the CCS, so the
+    /// verifier count is an estimate, which does not include the transcript hashing.
+    pub fn cost_model(&self) -> CostModel {
+        let round_degree = self.d + 1;
+        let multisets_len: usize = self.S.iter().map(|S_i| S_i.len()).sum();
+        // each round evaluates its polynomial from its round_degree + 1 evaluations
+        let sumcheck_ops = self.s * (round_degree + 1);
+        // eq(r_x, r_x') and eq(beta, r_x') take about 3 multiplications per variable
+        let eq_ops = 2 * 3 * self.s;
+        // \sum_j gamma^j * sigma_j, and \sum_i c_i * \prod_{j \in S_i} theta_j
+        let claimed_eval_ops = 2 * self.t + multisets_len + self.q;
+        // u, x and v of the folded instance
+        let fold_ops = 1 + self.l + self.t;
+        CostModel {
+            sumcheck_rounds: self.s,
+            round_degree,
+            proof_size: self.s * (round_degree + 1) + 2 * self.t,
+            commitment_group_ops: 2,
+            verifier_field_ops: sumcheck_ops + eq_ops + claimed_eval_ops + fold_ops,
+        }
+    }
+
+    /// Return an equivalent CCS where identical matrices are merged into one, with the multisets
+    /// pointing at the first of them, and where the matrices not referenced by any multiset are
+    /// dropped. This reduces t, and with it the number of M_j z products computed by the prover.
+    ///
+    /// Two matrices are identical when they have the same nonzero entries, regardless of the order
+    /// of the entries within their rows.
+    pub fn simplify(&self) -> Self {
+        let normalized: Vec<Vec<Vec<(F, usize)>>> = self
+            .M
+            .iter()
+            .map(|M_j| {
+                M_j.coeffs
+                    .iter()
+                    .map(|row| {
+                        let mut row: Vec<(F, usize)> =
+                            row.iter().filter(|(v, _)| !v.is_zero()).copied().collect();
+                        row.sort_by_key(|&(_, col)| col);
+                        row
+                    })
+                    .collect()
+            })
+            .collect();
+        // index of the first matrix identical to each matrix
+        let canonical: Vec<usize> = (0..self.t)
+            .map(|j| {
+                (0..j)
+                    .find(|&k| normalized[k] == normalized[j])
+                    .unwrap_or(j)
+            })
+            .collect();
+
+        // keep the canonical matrices referenced by the multisets, in their original order
+        let mut referenced = vec![false; self.t];
+        for &j in self.S.iter().flatten() {
+            referenced[canonical[j]] = true;
+        }
+        let mut new_index = vec![0; self.t];
+        let mut M = vec![];
+        for j in (0..self.t).filter(|&j| referenced[j]) {
+            new_index[j] = M.len();
+            M.push(self.M[j].clone());
+        }
+        let S: Vec<Vec<usize>> = self
+            .S
+            .iter()
+            .map(|S_i| S_i.iter().map(|&j| new_index[canonical[j]]).collect())
+            .collect();
+
+        CCS {
+            t: M.len(),
+            M,
+            S,
+            ..self.clone()
+        }
+    }
+
+    /// Return the CCS padded to m = 2^s rows and n = 2^s' columns, with empty rows and columns.
+    ///
+    /// The extra columns are appended at the end of z, so they belong to the witness w and the
+    /// public io keeps its length l. A z vector satisfying the CCS, extended with zeros up to the
+    /// new n, satisfies the padded CCS.
+    pub fn pad_to_pow2(&self) -> Self {
+        let m = 1 << self.s;
+        let n = 1 << self.s_prime;
+        let M = self
+            .M
+            .iter()
+            .map(|M_j| {
+                let mut coeffs = M_j.coeffs.clone();
+                coeffs.resize(m, vec![]);
+                SparseMatrix {
+                    n_rows: m,
+                    n_cols: n,
+                    coeffs,
+                }
+            })
+            .collect();
         CCS {
             m,
             n,
-            l: io_len,
-            s: log2(m) as usize,
-            s_prime: log2(n) as usize,
-            t: 3,
-            q: 2,
-            d: 2,
+            M,
+            ..self.clone()
+        }
+    }
+
+    /// Return the SHA-256 hash of the serialized CCS structure: its dimensions, matrices,
+    /// multisets and coefficients. Hashing the matrices is linear in their number of nonzero
+    /// entries, so callers absorbing the same CCS many times should compute it once and use
+    /// [`Self::absorb_digest`].
+    pub fn digest(&self) -> [u8; 32] {
+        let mut bytes = vec![];
+        self.serialize_compressed(&mut bytes).unwrap();
+        Sha256::digest(&bytes).into()
+    }
+
+    /// Absorb the CCS structure into the transcript through its [`Self::digest`], so that the
+    /// challenges derived from it are bound to the CCS: the same instances of two different CCS
+    /// give different challenges.
+    pub fn absorb_into(&self, transcript: &mut IOPTranscript<F>) {
+        Self::absorb_digest(transcript, &self.digest());
+    }
+
+    /// Absorb a digest computed by [`Self::digest`] into the transcript, which is the same as
+    /// [`Self::absorb_into`] on the CCS it was computed from
+    pub fn absorb_digest(transcript: &mut IOPTranscript<F>, digest: &[u8; 32]) {
+        transcript.append_message(b"ccs digest", digest).unwrap();
+    }
+
+    /// Return the length n - l - 1 of the witness w in z = (1, x, w), which is the number of
+    /// commitment generators the instances need, or an error if the CCS has no witness entries,
+    /// instead of underflowing into a huge length when n <= l + 1
+    pub fn witness_len(&self) -> Result<usize, CCSError> {
+        if self.n <= self.l + 1 {
+            return Err(CCSError::EmptyWitness {
+                n: self.n,
+                l: self.l,
+            });
+        }
+        Ok(self.n - self.l - 1)
+    }
+
+    /// Check that z is of length n, so that it splits into (1, x, w)
+    pub fn check_z_length(&self, z: &[F]) -> Result<(), CCSError> {
+        if z.len() != self.n {
+            return Err(CCSError::InvalidWitnessLength {
+                expected: self.n,
+                got: z.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Compute the M_j * z vectors for all j values in 0..t, in parallel. They are the evaluation
+    /// tables over the hypercube of \sum_{y \in {0,1}^s'} M_j(x, y) * z(y), out of which the v_j,
+    /// sigmas, thetas and L_j polynomials are computed. Return an error if z is not of length n.
+    pub fn compute_Mz_vectors(&self, z: &[F]) -> Result<Vec<Vec<F>>, CCSError> {
+        self.check_z_length(z)?;
+        Ok(cfg_iter!(self.M)
+            .map(|M_j| mat_vec_mul_sparse(M_j, z))
+            .collect())
+    }
+
+    /// Build the z = (u, x, w) vector, checking that x is of length l and w of length n - l - 1
+    pub fn assemble_z(&self, u: F, x: &[F], w: &[F]) -> Result<Vec<F>, CCSError> {
+        if x.len() != self.l {
+            return Err(CCSError::InvalidPublicInputLength {
+                expected: self.l,
+                got: x.len(),
+            });
+        }
+        if w.len() != self.n - self.l - 1 {
+            return Err(CCSError::InvalidWLength {
+                expected: self.n - self.l - 1,
+                got: w.len(),
+            });
+        }
+        Ok([vec![u], x.to_vec(), w.to_vec()].concat())
+    }
+
+    /// Return the evaluations of q(x) = \sum c_i * \prod_{j \in S_i} ( \sum_y M_j(x, y) * z(y) )
+    /// over the boolean hypercube {0,1}^s, in little-endian order. Return an error if z is not of
+    /// length n.
+    ///
+    /// This does not fold the MLEs one variable at a time. Fixing all the variables of
+    /// \sum_y M_j(x, y) * z(y) to the bits of a point of the hypercube selects the entry of the
+    /// M_j z vector at the index of the point, so the 2^s evaluations are the entries of the
+    /// \sum c_i * \prod_{j \in S_i} M_j z vector, padded with zeros. Computing each M_j z product
+    /// once and combining them row by row gives the same values as the fold, with no folding
+    /// rounds at all.
+    pub fn eval_q_on_hypercube(&self, z: &[F]) -> Result<Vec<F>, CCSError> {
+        let vec_Mz = self.compute_Mz_vectors(z)?;
+
+        let mut evals = vec![F::zero(); 1 << self.s];
+        for (S_i, c_i) in self.S.iter().zip(self.c.iter()) {
+            for (row, eval) in evals.iter_mut().enumerate().take(self.m) {
+                let prod: F = S_i.iter().map(|j| vec_Mz[*j][row]).product();
+                *eval += *c_i * prod;
+            }
+        }
+        Ok(evals)
+    }
 
-            S: vec![vec![0, 1], vec![2]],
-            c: vec![C::ScalarField::one(), C::ScalarField::one().neg()],
-            M: vec![A, B, C],
+    /// Return the value \sum c_i * \prod_{j \in S_i} (M_j z)[row] of a single constraint, which is
+    /// the evaluation of q(x) at the point of the hypercube of index `row`. Return an error if z is
+    /// not of length n or the row is not one of the m constraints.
+    ///
+    /// This only touches the given row of the matrices, so it is cheap enough to inspect the
+    /// failing constraint reported by [`Self::check_relation`], which evaluates all of them at
+    /// once with [`Self::eval_q_on_hypercube`].
+    pub fn eval_row(&self, z: &[F], row: usize) -> Result<F, CCSError> {
+        self.check_z_length(z)?;
+        if row >= self.m {
+            return Err(CCSError::RowOutOfRange { row, m: self.m });
         }
+        let row_Mz: Vec<F> = self
+            .M
+            .iter()
+            .map(|M_j| {
+                M_j.coeffs[row]
+                    .iter()
+                    .map(|&(value, col)| value * z[col])
+                    .sum()
+            })
+            .collect();
+        Ok(self
+            .S
+            .iter()
+            .zip(self.c.iter())
+            .map(|(S_i, c_i)| *c_i * S_i.iter().map(|j| row_Mz[*j]).product::<F>())
+            .sum())
     }
 
+    /// Check that a CCS structure is satisfied by a z vector, which is when q(x) evaluates to zero
+    /// over the boolean hypercube. Return an error if z is not of length n.
+    /// This works with matrices. It doesn't do any polynomial stuff, so it's cheaper than
+    /// evaluating q(x) over the boolean hypercube point by point.
+    pub fn check_relation(&self, z: &[F]) -> Result<(), CCSError> {
+        let result = self.eval_q_on_hypercube(z)?;
+
+        // Make sure the final vector is all zeroes, reporting the first constraint that is not
+        if let Some((row, value)) = result.iter().enumerate().find(|(_, e)| !e.is_zero()) {
+            return Err(CCSError::NotSatisfied {
+                row,
+                point: (0..self.s).map(|k| ((row >> k) & 1) as u8).collect(),
+                value: value.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Return whether the z vector satisfies the CCS relation
+    pub fn is_satisfied(&self, z: &[F]) -> bool {
+        self.check_relation(z).is_ok()
+    }
+
+    /// Return whether z = (1, 0, ..., 0), with zero public io and witness, satisfies the CCS.
+    ///
+    /// This is the case of CCS whose constraints are all homogeneous in the entries of x and w,
+    /// i.e. none of them uses the constant entry of z to add a constant term, such as the squaring
+    /// chain x_{i+1} = x_i^2. The zero assignment then satisfies the CCS whatever the circuit
+    /// computes, so a test folding it passes even if the folding mixes up the witnesses, and says
+    /// nothing about the soundness of the scheme. Test harnesses can use this to warn about such
+    /// CCS, and [`Self::check_relation_nontrivial`] to reject the zero witness.
+    pub fn is_trivially_satisfiable(&self) -> bool {
+        let mut z = vec![F::zero(); self.n];
+        z[0] = F::one();
+        self.is_satisfied(&z)
+    }
+
+    /// Same as [`Self::check_relation`], also returning `CCSError::ZeroWitness` if the witness
+    /// part w of z = (1, x, w) is all zeros, see [`Self::is_trivially_satisfiable`]
+    pub fn check_relation_nontrivial(&self, z: &[F]) -> Result<(), CCSError> {
+        self.check_z_length(z)?;
+        if z[self.l + 1..].iter().all(|w_i| w_i.is_zero()) {
+            return Err(CCSError::ZeroWitness);
+        }
+        self.check_relation(z)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::ccs::util::compute_all_sum_Mz_evals;
+    use crate::util::hypercube::BooleanHypercube;
+    use crate::util::math::log2_exact;
+    use crate::util::mle::{matrix_to_mle, vec_to_mle};
+    use ark_bls12_381::Fr;
+    use ark_poly::MultilinearExtension;
+    use ark_std::{rand::Rng, test_rng, UniformRand};
+
     /// Return a CCS circuit that implements the Vitalik `x^3 + x + 5 == 35` (from
     /// https://www.vitalik.ca/general/2016/12/10/qap.html )
     #[cfg(test)]
-    pub fn get_test_ccs<C: CurveGroup>() -> CCS<C> {
+    pub fn get_test_ccs<F: PrimeField>() -> CCS<F> {
         let A = to_F_matrix(vec![
             vec![0, 1, 0, 0, 0, 0],
             vec![0, 0, 0, 1, 0, 0],
@@ -133,7 +879,13 @@ pub mod test {
             vec![0, 0, 0, 0, 0, 1],
             vec![0, 0, 1, 0, 0, 0],
         ]);
-        CCS_from_r1cs(A, B, C, 1)
+        CCS::from_r1cs(
+            SparseMatrix::from_dense(&A),
+            SparseMatrix::from_dense(&B),
+            SparseMatrix::from_dense(&C),
+            1,
+        )
+        .unwrap()
     }
 
     /// Computes the z vector for the given input for Vitalik's equation.
@@ -150,12 +902,835 @@ pub mod test {
         ])
     }
 
+    /// Return a CCS circuit that implements `x^3 + x + 5 == out` with a single cubic custom gate:
+    /// M_0 z ∘ M_1 z ∘ M_2 z + M_3 z - M_4 z = 0, with M_0 = M_1 = M_2 selecting x, M_3 z = x + 5 and
+    /// M_4 selecting out. The gate row is padded with an empty row.
+    #[cfg(test)]
+    pub fn get_test_ccs_cubic<F: PrimeField>() -> CCS<F> {
+        let X = to_F_matrix(vec![vec![0, 1, 0], vec![0, 0, 0]]);
+        let X_plus_5 = to_F_matrix(vec![vec![5, 1, 0], vec![0, 0, 0]]);
+        let OUT = to_F_matrix(vec![vec![0, 0, 1], vec![0, 0, 0]]);
+        CCS::new(
+            vec![
+                SparseMatrix::from_dense(&X),
+                SparseMatrix::from_dense(&X),
+                SparseMatrix::from_dense(&X),
+                SparseMatrix::from_dense(&X_plus_5),
+                SparseMatrix::from_dense(&OUT),
+            ],
+            vec![vec![0, 1, 2], vec![3], vec![4]],
+            vec![F::one(), F::one(), F::one().neg()],
+            1,
+        )
+        .unwrap()
+    }
+
+    /// Computes the z vector of the cubic CCS for the given input.
+    #[cfg(test)]
+    pub fn get_test_z_cubic<F: PrimeField>(input: usize) -> Vec<F> {
+        // z = (1, io, w)
+        to_F_vec(vec![1, input, input * input * input + input + 5])
+    }
+
+    /// Return a CCS circuit that implements `x * y - 2x == out` with a single gate
+    /// M_0 z ∘ M_1 z - 2 M_0 z - M_2 z = 0, with M_0, M_1 and M_2 selecting x, y and out, over the
+    /// z vector (1, out, x, y). The gate row is padded with an empty row.
+    #[cfg(test)]
+    pub fn get_test_ccs_signed<F: PrimeField>() -> CCS<F> {
+        let X = to_F_matrix(vec![vec![0, 0, 1, 0], vec![0, 0, 0, 0]]);
+        let Y = to_F_matrix(vec![vec![0, 0, 0, 1], vec![0, 0, 0, 0]]);
+        let OUT = to_F_matrix(vec![vec![0, 1, 0, 0], vec![0, 0, 0, 0]]);
+        CCS::with_signed_constants(
+            vec![
+                SparseMatrix::from_dense(&X),
+                SparseMatrix::from_dense(&Y),
+                SparseMatrix::from_dense(&OUT),
+            ],
+            vec![vec![0, 1], vec![0], vec![2]],
+            &[1, -2, -1],
+            1,
+        )
+        .unwrap()
+    }
+
+    /// Computes the z vector of the signed CCS for the given x and y, with y >= 2
+    #[cfg(test)]
+    pub fn get_test_z_signed<F: PrimeField>(x: usize, y: usize) -> Vec<F> {
+        // z = (1, io, w)
+        to_F_vec(vec![1, x * y - 2 * x, x, y])
+    }
+
+    /// Return an R1CS circuit of `num_constraints` constraints over z vectors of `num_vars`
+    /// entries, z = (1, x, x^2, x^4, ...), for scaling tests. The constraint k squares the wire
+    /// 1 + k into the wire 2 + k, wrapping around the wires when there are more constraints than
+    /// wires. The z vectors are given by [`get_test_z_of_size`].
+    #[cfg(test)]
+    pub fn get_test_ccs_of_size<F: PrimeField>(num_constraints: usize, num_vars: usize) -> CCS<F> {
+        assert!(num_vars >= 3);
+        let wires = num_vars - 2;
+        let selector = |offset: usize| SparseMatrix {
+            n_rows: num_constraints,
+            n_cols: num_vars,
+            coeffs: (0..num_constraints)
+                .map(|k| vec![(F::one(), offset + k % wires)])
+                .collect(),
+        };
+        CCS::from_r1cs(selector(1), selector(1), selector(2), 1).unwrap()
+    }
+
+    /// Computes the z vector of a [`get_test_ccs_of_size`] circuit of `num_vars` entries for the
+    /// given input
+    #[cfg(test)]
+    pub fn get_test_z_of_size<F: PrimeField>(num_vars: usize, input: usize) -> Vec<F> {
+        let mut z = vec![F::one(), F::from(input as u64)];
+        while z.len() < num_vars {
+            z.push(z.last().unwrap().square());
+        }
+        z
+    }
+
+    /// Return a random R1CS circuit of `gates` multiplication gates over l public inputs, where
+    /// the k-th gate computes the wire z_{1+l+k} as the product of two random linear combinations
+    /// of the previous entries of z = (1, io, w). Any public input can then be extended to a
+    /// satisfying z vector with [`get_random_z`].
+    #[cfg(test)]
+    pub fn get_random_ccs<F: PrimeField, R: Rng>(rng: &mut R, l: usize, gates: usize) -> CCS<F> {
+        let n = 1 + l + gates;
+        let mut lincomb = |wire: usize| -> Vec<(F, usize)> {
+            let mut row = vec![];
+            for col in 0..wire {
+                if rng.gen_bool(0.5) {
+                    row.push((F::rand(rng), col));
+                }
+            }
+            row
+        };
+        let (mut A, mut B, mut C) = (vec![], vec![], vec![]);
+        for k in 0..gates {
+            let wire = 1 + l + k;
+            A.push(lincomb(wire));
+            B.push(lincomb(wire));
+            C.push(vec![(F::one(), wire)]);
+        }
+        let matrix = |coeffs| SparseMatrix {
+            n_rows: gates,
+            n_cols: n,
+            coeffs,
+        };
+        CCS::from_r1cs(matrix(A), matrix(B), matrix(C), l).unwrap()
+    }
+
+    /// Computes the z vector of a [`get_random_ccs`] circuit for the public input x, by
+    /// evaluating its gates in order
+    #[cfg(test)]
+    pub fn get_random_z<F: PrimeField>(ccs: &CCS<F>, x: &[F]) -> Vec<F> {
+        let mut z = [vec![F::one()], x.to_vec()].concat();
+        let lincomb = |row: &[(F, usize)], z: &[F]| -> F {
+            row.iter().map(|(value, col)| *value * z[*col]).sum()
+        };
+        for k in 0..ccs.m {
+            let wire = lincomb(&ccs.M[0].coeffs[k], &z) * lincomb(&ccs.M[1].coeffs[k], &z);
+            z.push(wire);
+        }
+        z
+    }
+
+    #[test]
+    fn test_ccs_stats() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let stats = ccs.stats();
+        assert_eq!(
+            (stats.m, stats.n, stats.l, stats.t, stats.q, stats.d),
+            (4, 6, 1, 3, 2, 2)
+        );
+        assert_eq!((stats.s, stats.s_prime), (2, 3));
+        assert_eq!(stats.nonzeros, vec![6, 4, 4]);
+        // 4 hypercube points, and the multisets {A, B} and {C}
+        assert_eq!(stats.hypercube_work, 4 * 3);
+        assert_eq!(
+            stats.to_string(),
+            "CCS of m = 4 constraints (s = 2) and n = 6 variables (s' = 3), l = 1\n\
+             t = 3 matrices, q = 2 multisets of degree d = 2\n\
+             nonzeros: [6, 4, 4], 14 in total (19.44% dense)\n\
+             hypercube work: 12 products"
+        );
+
+        // padding keeps the nonzeros, while the hypercube work only depends on s
+        let padded_stats = get_test_ccs_of_size::<Fr>(5, 6).pad_to_pow2().stats();
+        assert_eq!(padded_stats.nonzeros, vec![5, 5, 5]);
+        assert_eq!(padded_stats.hypercube_work, 8 * 3);
+    }
+
+    #[test]
+    fn test_ccs_cost_model() -> () {
+        let cost = get_test_ccs::<Fr>().cost_model();
+        assert_eq!(
+            cost,
+            CostModel {
+                sumcheck_rounds: 2,
+                round_degree: 3,
+                proof_size: 2 * 4 + 2 * 3,
+                commitment_group_ops: 2,
+                // sumcheck 2 * 4, eq polynomials 2 * 3 * 2, claimed evaluation 2 * 3 + 3 + 2 and
+                // fold 1 + 1 + 3
+                verifier_field_ops: 8 + 12 + 11 + 5,
+            }
+        );
+        assert_eq!(
+            cost.to_string(),
+            "sumcheck: 2 rounds of degree 3\n\
+             proof size: 14 field elements\n\
+             commitment group ops: 2\n\
+             verifier field ops: 36"
+        );
+
+        // the cubic gate has rounds of a higher degree, but a single constraint row
+        let cubic_cost = get_test_ccs_cubic::<Fr>().cost_model();
+        assert_eq!(
+            (cubic_cost.sumcheck_rounds, cubic_cost.round_degree),
+            (1, 4)
+        );
+        assert_eq!(cubic_cost.proof_size, 5 + 2 * 5);
+    }
+
+    #[test]
+    fn test_ccs_of_size() -> () {
+        for (num_constraints, num_vars) in [(1, 3), (4, 8), (16, 6), (5, 100), (1 << 10, 1 << 10)] {
+            let ccs = get_test_ccs_of_size::<Fr>(num_constraints, num_vars);
+            assert_eq!((ccs.m, ccs.n, ccs.l), (num_constraints, num_vars, 1));
+
+            let z = get_test_z_of_size(num_vars, 3);
+            ccs.check_relation(&z).unwrap();
+            let mut bad_z = z.clone();
+            bad_z[2] += Fr::one();
+            assert!(ccs.check_relation(&bad_z).is_err());
+        }
+    }
+
+    /// The MLEs of the M_j * z vectors evaluate at r to the sums computed by
+    /// compute_all_sum_Mz_evals
+    #[test]
+    fn test_ccs_compute_Mz_vectors() -> () {
+        let mut rng = test_rng();
+        for (ccs, z) in [
+            (get_test_ccs::<Fr>(), get_test_z(3)),
+            (get_test_ccs_cubic::<Fr>(), get_test_z_cubic(3)),
+            (get_test_ccs_of_size::<Fr>(13, 7), get_test_z_of_size(7, 2)),
+        ] {
+            let vec_Mz = ccs.compute_Mz_vectors(&z).unwrap();
+            assert_eq!(vec_Mz.len(), ccs.t);
+            for (M_j, Mz_j) in ccs.M.iter().zip(vec_Mz.iter()) {
+                assert_eq!(Mz_j, &mat_vec_mul(&M_j.to_dense(), &z));
+            }
+
+            let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+            let evals: Vec<Fr> = vec_Mz
+                .iter()
+                .map(|Mz_j| vec_to_mle(ccs.s, Mz_j).evaluate(&r).unwrap())
+                .collect();
+            assert_eq!(
+                evals,
+                compute_all_sum_Mz_evals(&ccs.M, &z, &r, ccs.s).unwrap()
+            );
+
+            assert!(matches!(
+                ccs.compute_Mz_vectors(&z[1..]),
+                Err(CCSError::InvalidWitnessLength { .. })
+            ));
+        }
+    }
+
     /// Test that a basic CCS relation can be satisfied
     #[test]
     fn test_ccs_relation() -> () {
-        let ccs = get_test_ccs::<G1Projective>();
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+
+        ccs.check_relation(&z).unwrap();
+    }
+
+    /// Test that a satisfying R1CS witness satisfies the CCS obtained from the R1CS, and that a
+    /// non satisfying one does not
+    #[test]
+    fn test_ccs_from_r1cs() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let z: Vec<Fr> = get_test_z(3);
+
+        // check the R1CS relation Az ∘ Bz = Cz directly
+        let Az = mat_vec_mul(&ccs.M[0].to_dense(), &z);
+        let Bz = mat_vec_mul(&ccs.M[1].to_dense(), &z);
+        let Cz = mat_vec_mul(&ccs.M[2].to_dense(), &z);
+        assert_eq!(hadamard(&Az, &Bz), Cz);
+
+        assert_eq!(ccs.m, 4);
+        assert_eq!(ccs.n, 6);
+        assert_eq!(ccs.s, 2);
+        assert_eq!(ccs.s_prime, 3);
+        ccs.check_relation(&z).unwrap();
+
+        let mut bad_z = z.clone();
+        bad_z[2] += Fr::one();
+        assert!(ccs.check_relation(&bad_z).is_err());
+    }
+
+    /// The CCS with a constant term in their constraints are not satisfied by the zero witness,
+    /// while the homogeneous ones are
+    #[test]
+    fn test_ccs_trivially_satisfiable() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        assert!(!ccs.is_trivially_satisfiable());
+        assert!(!get_test_ccs_cubic::<Fr>().is_trivially_satisfiable());
+        ccs.check_relation_nontrivial(&get_test_z(3)).unwrap();
+
+        for (ccs, z) in [
+            (get_test_ccs_of_size::<Fr>(5, 6), get_test_z_of_size(6, 3)),
+            (get_test_ccs_signed::<Fr>(), get_test_z_signed(5, 3)),
+        ] {
+            assert!(ccs.is_trivially_satisfiable());
+            let mut zero_z = vec![Fr::zero(); ccs.n];
+            zero_z[0] = Fr::one();
+            ccs.check_relation(&zero_z).unwrap();
+            assert!(matches!(
+                ccs.check_relation_nontrivial(&zero_z),
+                Err(CCSError::ZeroWitness)
+            ));
+
+            ccs.check_relation_nontrivial(&z).unwrap();
+            let mut bad_z = z.clone();
+            bad_z[2] += Fr::one();
+            assert!(matches!(
+                ccs.check_relation_nontrivial(&bad_z),
+                Err(CCSError::NotSatisfied { .. })
+            ));
+        }
+    }
+
+    /// The CCS rebuilt from the MLE tables of the matrices is the padded CCS
+    #[test]
+    fn test_ccs_from_mle_tables() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let tables: Vec<Vec<Fr>> = ccs
+            .M
+            .iter()
+            .map(|M_j| matrix_to_mle(M_j.to_dense()).evaluations)
+            .collect();
+        let rebuilt = CCS::from_mle_tables(
+            tables.clone(),
+            ccs.s,
+            ccs.s_prime,
+            ccs.l,
+            ccs.S.clone(),
+            ccs.c.clone(),
+        )
+        .unwrap();
+        assert_eq!(rebuilt, ccs.pad_to_pow2());
+        assert_eq!((rebuilt.m, rebuilt.n), (4, 8));
+
+        let mut z = get_test_z(3);
+        z.resize(rebuilt.n, Fr::zero());
+        rebuilt.check_relation(&z).unwrap();
+        z[2] += Fr::one();
+        assert!(rebuilt.check_relation(&z).is_err());
+
+        let mut bad_tables = tables;
+        bad_tables[1].pop();
+        assert!(matches!(
+            CCS::from_mle_tables(bad_tables, ccs.s, ccs.s_prime, ccs.l, ccs.S, ccs.c),
+            Err(CCSError::InvalidMleTableLength {
+                index: 1,
+                expected: 32,
+                got: 31
+            })
+        ));
+
+        // the error reports the number of variables, as 2^(s + s') does not fit in a usize
+        assert!(matches!(
+            CCS::<Fr>::from_mle_tables(vec![], 40, 30, 1, vec![], vec![]),
+            Err(CCSError::MleTablesTooLarge { s: 40, s_prime: 30 })
+        ));
+        assert!(matches!(
+            CCS::<Fr>::from_mle_tables(vec![], usize::MAX, 1, 1, vec![], vec![]),
+            Err(CCSError::MleTablesTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ccs_pad_to_pow2() -> () {
+        // add a fifth constraint x * x = x^2 to the test CCS, so that it is 5x6
+        let ccs = get_test_ccs::<Fr>();
+        let M: Vec<SparseMatrix<Fr>> = ccs
+            .M
+            .iter()
+            .map(|M_j| {
+                let mut coeffs = M_j.coeffs.clone();
+                coeffs.push(M_j.coeffs[0].clone());
+                SparseMatrix {
+                    n_rows: 5,
+                    n_cols: 6,
+                    coeffs,
+                }
+            })
+            .collect();
+        let ccs = CCS::new(M, ccs.S, ccs.c, ccs.l).unwrap();
+        assert_eq!((ccs.m, ccs.n, ccs.s, ccs.s_prime), (5, 6, 3, 3));
+
+        let padded = ccs.pad_to_pow2();
+        padded.check().unwrap();
+        assert_eq!((padded.m, padded.n, padded.s, padded.s_prime), (8, 8, 3, 3));
+        assert_eq!(log2_exact(padded.m).unwrap(), padded.s);
+        assert_eq!(log2_exact(padded.n).unwrap(), padded.s_prime);
+        assert!(log2_exact(ccs.m).is_err());
+        assert_eq!(
+            (padded.l, padded.t, padded.q, padded.d),
+            (ccs.l, ccs.t, ccs.q, ccs.d)
+        );
+
+        // z satisfies the padded CCS once extended with zeros
+        let z = get_test_z::<Fr>(3);
+        ccs.check_relation(&z).unwrap();
+        let mut padded_z = z.clone();
+        padded_z.resize(padded.n, Fr::zero());
+        padded.check_relation(&padded_z).unwrap();
+        let mut bad_z = padded_z.clone();
+        bad_z[2] += Fr::one();
+        assert!(padded.check_relation(&bad_z).is_err());
+
+        // padding is a no-op for power of two dimensions
+        let padded_twice = padded.pad_to_pow2();
+        assert_eq!(padded_twice, padded);
+    }
+
+    /// The evaluations of q(x) over the hypercube are the same as evaluating the q(x) virtual
+    /// polynomial point by point
+    #[test]
+    fn test_eval_q_on_hypercube() -> () {
+        let mut rng = test_rng();
+
+        for (ccs, z) in [
+            (get_test_ccs::<Fr>(), get_test_z(3)),
+            (get_test_ccs_cubic::<Fr>(), get_test_z_cubic(3)),
+        ] {
+            let random_z: Vec<Fr> = (0..ccs.n).map(|_| Fr::rand(&mut rng)).collect();
+            for z in [z, random_z] {
+                let q = ccs.q_from_plan(&ccs.prepare_q(), &z);
+                let naive: Vec<Fr> = BooleanHypercube::new(ccs.s)
+                    .map(|x| q.evaluate(&x).unwrap())
+                    .collect();
+                assert_eq!(ccs.eval_q_on_hypercube(&z).unwrap(), naive);
+            }
+        }
+        assert!(get_test_ccs::<Fr>()
+            .eval_q_on_hypercube(&get_test_z(3))
+            .unwrap()
+            .iter()
+            .all(|e| e.is_zero()));
+
+        // a short z is an error instead of a panic in the matrix-vector products
         let z = get_test_z(3);
+        assert!(matches!(
+            get_test_ccs::<Fr>().eval_q_on_hypercube(&z[1..]),
+            Err(CCSError::InvalidWitnessLength { .. })
+        ));
+        assert!(matches!(
+            get_test_ccs::<Fr>().check_relation(&z[1..]),
+            Err(CCSError::InvalidWitnessLength { .. })
+        ));
+    }
+
+    /// Every row of a satisfying z evaluates to zero, and the rows of an unsatisfying z are the
+    /// evaluations of q(x) over the hypercube
+    #[test]
+    fn test_ccs_eval_row() -> () {
+        for (ccs, z) in [
+            (get_test_ccs::<Fr>(), get_test_z(3)),
+            (get_test_ccs_cubic::<Fr>(), get_test_z_cubic(3)),
+            (get_test_ccs_of_size::<Fr>(13, 7), get_test_z_of_size(7, 2)),
+        ] {
+            for row in 0..ccs.m {
+                assert!(ccs.eval_row(&z, row).unwrap().is_zero());
+            }
+
+            let mut bad_z = z.clone();
+            bad_z[2] += Fr::one();
+            let evals = ccs.eval_q_on_hypercube(&bad_z).unwrap();
+            for row in 0..ccs.m {
+                assert_eq!(ccs.eval_row(&bad_z, row).unwrap(), evals[row]);
+            }
+            let row = match ccs.check_relation(&bad_z) {
+                Err(CCSError::NotSatisfied { row, .. }) => row,
+                _ => panic!("bad z satisfies the CCS"),
+            };
+            assert!(!ccs.eval_row(&bad_z, row).unwrap().is_zero());
+
+            assert!(matches!(
+                ccs.eval_row(&z, ccs.m),
+                Err(CCSError::RowOutOfRange { .. })
+            ));
+            assert!(matches!(
+                ccs.eval_row(&z[1..], 0),
+                Err(CCSError::InvalidWitnessLength { .. })
+            ));
+        }
+    }
+
+    /// A non satisfied relation reports the first failing constraint and the value it evaluates to
+    #[test]
+    fn test_ccs_not_satisfied() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        // out = x^3 + x + 5 is checked by the last constraint, which evaluates to 35 - 36
+        let mut bad_z: Vec<Fr> = get_test_z(3);
+        bad_z[2] += Fr::one();
+
+        let e = ccs.check_relation(&bad_z).unwrap_err();
+        let expected_value = (-Fr::one()).to_string();
+        assert!(matches!(
+            &e,
+            CCSError::NotSatisfied { row: 3, point, value } if point == &vec![1, 1] && value == &expected_value
+        ));
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "Relation not satisfied at row 3, the hypercube point [1, 1], where it evaluates to {}",
+                expected_value
+            )
+        );
+    }
+
+    /// Express the Vitalik `x^3 + x + 5 == 35` circuit as Plonkish gates, and check that its CCS is
+    /// satisfied exactly by the z vectors satisfying the gates
+    #[test]
+    fn test_ccs_from_plonkish() -> () {
+        let gate = |q_L: i64, q_R: i64, q_O: i64, q_M: i64, q_C: i64| PlonkishGate::<Fr> {
+            q_L: Fr::from(q_L),
+            q_R: Fr::from(q_R),
+            q_O: Fr::from(q_O),
+            q_M: Fr::from(q_M),
+            q_C: Fr::from(q_C),
+        };
+        // z = (1, out, x, x^2, x^3)
+        let gates = vec![
+            gate(0, 0, -1, 1, 0), // x * x - x^2 = 0
+            gate(0, 0, -1, 1, 0), // x^2 * x - x^3 = 0
+            gate(1, 1, -1, 0, 5), // x^3 + x + 5 - out = 0
+        ];
+        let wiring = vec![[2, 2, 3], [3, 2, 4], [4, 2, 1]];
+        let ccs = CCS::from_plonkish(&gates, &wiring, 1).unwrap();
+        assert_eq!((ccs.m, ccs.n, ccs.l, ccs.degree()), (3, 5, 1, 2));
+
+        let eval_gates = |z: &[Fr]| {
+            gates.iter().zip(wiring.iter()).all(|(g, [a, b, c])| {
+                (g.q_L * z[*a] + g.q_R * z[*b] + g.q_O * z[*c] + g.q_M * z[*a] * z[*b] + g.q_C)
+                    .is_zero()
+            })
+        };
+        let z: Vec<Fr> = to_F_vec(vec![1, 35, 3, 9, 27]);
+        let mut bad_z = z.clone();
+        bad_z[4] += Fr::one();
+        for z in [z, bad_z] {
+            assert_eq!(ccs.is_satisfied(&z), eval_gates(&z));
+        }
+        assert!(ccs.is_satisfied(&to_F_vec(vec![1, 35, 3, 9, 27])));
+
+        assert!(matches!(
+            CCS::from_plonkish(&gates, &wiring[..2], 1),
+            Err(CCSError::InvalidWiringLength { gates: 3, got: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_ccs_assemble_z() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let z: Vec<Fr> = get_test_z(3);
+        let (x, w) = (&z[1..2], &z[2..]);
+        assert_eq!(ccs.assemble_z(Fr::one(), x, w).unwrap(), z);
+
+        assert!(matches!(
+            ccs.assemble_z(Fr::one(), &z[1..3], &z[3..]),
+            Err(CCSError::InvalidPublicInputLength {
+                expected: 1,
+                got: 2
+            })
+        ));
+        assert!(matches!(
+            ccs.assemble_z(Fr::one(), x, &z[3..]),
+            Err(CCSError::InvalidWLength {
+                expected: 4,
+                got: 3
+            })
+        ));
+    }
+
+    /// CCSError is a std error, so it can be boxed and displayed along with other errors
+    #[test]
+    fn test_ccs_error() -> () {
+        let e: Box<dyn std::error::Error> = Box::new(CCSError::VEvaluationMismatch { index: 2 });
+        assert_eq!(
+            e.to_string(),
+            "v_2 does not match the evaluation computed from the witness"
+        );
+    }
+
+    #[test]
+    fn test_ccs_digest() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        assert_eq!(ccs.digest(), ccs.clone().digest());
+        assert_ne!(ccs.digest(), get_test_ccs_cubic::<Fr>().digest());
+
+        // two CCS differing in a single matrix entry give different digests, and so different rho
+        // challenges
+        let mut other_ccs = ccs.clone();
+        other_ccs.M[2].coeffs[0][0].0 += Fr::one();
+        assert_ne!(ccs.digest(), other_ccs.digest());
+        let mut transcript_1 = IOPTranscript::<Fr>::new(b"ccs");
+        ccs.absorb_into(&mut transcript_1);
+        let mut transcript_2 = IOPTranscript::<Fr>::new(b"ccs");
+        other_ccs.absorb_into(&mut transcript_2);
+        assert_ne!(
+            transcript_1.get_and_append_challenge(b"rho").unwrap(),
+            transcript_2.get_and_append_challenge(b"rho").unwrap()
+        );
+
+        // absorbing the digest is the same as absorbing the CCS
+        let mut transcript_1 = IOPTranscript::<Fr>::new(b"ccs");
+        ccs.absorb_into(&mut transcript_1);
+        let mut transcript_2 = IOPTranscript::<Fr>::new(b"ccs");
+        CCS::absorb_digest(&mut transcript_2, &ccs.digest());
+        assert_eq!(
+            transcript_1.get_and_append_challenge(b"r").unwrap(),
+            transcript_2.get_and_append_challenge(b"r").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ccs_witness_len() -> () {
+        assert_eq!(get_test_ccs::<Fr>().witness_len().unwrap(), 4);
+        assert_eq!(get_test_ccs_cubic::<Fr>().witness_len().unwrap(), 1);
+
+        // all the entries of z but the constant are public
+        let A = SparseMatrix::from_dense(&to_F_matrix::<Fr>(vec![vec![0, 1], vec![0, 0]]));
+        let ccs = CCS::from_r1cs(A.clone(), A.clone(), A, 1).unwrap();
+        assert!(matches!(
+            ccs.witness_len(),
+            Err(CCSError::EmptyWitness { n: 2, l: 1 })
+        ));
+        // but such a z vector can still be assembled
+        assert_eq!(
+            ccs.assemble_z(Fr::one(), &[Fr::one()], &[]).unwrap(),
+            vec![Fr::one(), Fr::one()]
+        );
 
+        // n - l - 1 would underflow
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.l = 5;
+        assert!(matches!(
+            bad_ccs.witness_len(),
+            Err(CCSError::EmptyWitness { n: 2, l: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_ccs_invariants() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        ccs.check().unwrap();
+        assert_eq!((ccs.t, ccs.q, ccs.d), (3, 2, 2));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.s += 1;
+        assert!(matches!(bad_ccs.check(), Err(CCSError::InvalidS { .. })));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.s_prime += 1;
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidSPrime { .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.m = usize::MAX;
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::DimensionTooLarge { n: usize::MAX })
+        ));
+        let M = vec![SparseMatrix {
+            n_rows: usize::MAX,
+            n_cols: ccs.n,
+            coeffs: vec![],
+        }];
+        assert!(matches!(
+            CCS::new(M, vec![vec![0]], vec![Fr::one()], ccs.l),
+            Err(CCSError::DimensionTooLarge { .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.l = bad_ccs.n;
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidIOLength { .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.M.pop();
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidNumberOfMatrices { .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.M[1].n_rows += 1;
+        bad_ccs.M[1].coeffs.push(vec![]);
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidMatrixDimensions { index: 1, rows, .. }) if rows == ccs.m + 1
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.M[1].coeffs.pop();
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidMatrixRows { index: 1, n_rows, got })
+                if n_rows == ccs.m && got == ccs.m - 1
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.M[2].coeffs[0].push((Fr::one(), ccs.n));
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::MatrixEntryOutOfRange { index: 2, .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.S.pop();
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidNumberOfMultisets { .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.c.pop();
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::InvalidNumberOfCoefficients { .. })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.S[1] = vec![3];
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::MatrixIndexOutOfRange {
+                set: 1,
+                index: 3,
+                ..
+            })
+        ));
+
+        let mut bad_ccs = ccs.clone();
+        bad_ccs.S[1] = vec![0, 1, 2];
+        assert!(matches!(
+            bad_ccs.check(),
+            Err(CCSError::DegreeTooLarge { set: 1, .. })
+        ));
+    }
+
+    /// A malformed multiset is rejected when building the CCS, before it can reach compute_q
+    #[test]
+    fn test_ccs_new_bad_multiset() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let result = CCS::new(
+            ccs.M.clone(),
+            vec![vec![0, 4], vec![2]],
+            ccs.c.clone(),
+            ccs.l,
+        );
+        assert!(matches!(
+            result,
+            Err(CCSError::MatrixIndexOutOfRange {
+                set: 0,
+                index: 4,
+                t: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_ccs_cubic_gate() -> () {
+        let ccs = get_test_ccs_cubic::<Fr>();
+        assert_eq!(ccs.degree(), 3);
+        assert_eq!(ccs.d, 3);
+
+        ccs.check_relation(&get_test_z_cubic(3)).unwrap();
+        let mut bad_z = get_test_z_cubic::<Fr>(3);
+        bad_z[2] += Fr::one();
+        assert!(ccs.check_relation(&bad_z).is_err());
+
+        // the R1CS test circuit is quadratic
+        assert_eq!(get_test_ccs::<Fr>().degree(), 2);
+    }
+
+    /// The signed constants are mapped to their field elements, and the gate is only satisfied
+    /// thanks to its subtractive terms
+    #[test]
+    fn test_ccs_signed_constants() -> () {
+        let ccs = get_test_ccs_signed::<Fr>();
+        assert_eq!(ccs.c, vec![Fr::one(), -Fr::from(2u64), -Fr::one()]);
+        assert_eq!(ccs.d, 2);
+
+        let z = get_test_z_signed(5, 3);
         ccs.check_relation(&z).unwrap();
+        let mut bad_z = z.clone();
+        bad_z[1] += Fr::one();
+        assert!(ccs.check_relation(&bad_z).is_err());
+
+        // the same gate with the absolute values of the constants is not satisfied by z
+        let unsigned_ccs =
+            CCS::with_signed_constants(ccs.M.clone(), ccs.S.clone(), &[1, 2, 1], ccs.l).unwrap();
+        assert!(unsigned_ccs.check_relation(&z).is_err());
+
+        // the R1CS constants are the signed constants [1, -1]
+        let r1cs = get_test_ccs::<Fr>();
+        assert_eq!(
+            CCS::with_signed_constants(r1cs.M.clone(), r1cs.S.clone(), &[1, -1], r1cs.l).unwrap(),
+            r1cs
+        );
+        assert_eq!(
+            CCS::<Fr>::with_signed_constants(r1cs.M.clone(), r1cs.S, &[i64::MIN, -1], r1cs.l)
+                .unwrap()
+                .c[0],
+            -Fr::from(1u64 << 63)
+        );
+    }
+
+    #[test]
+    fn test_ccs_simplify() -> () {
+        // the cubic gate uses the same matrix three times
+        let ccs = get_test_ccs_cubic::<Fr>();
+        let simplified = ccs.simplify();
+        simplified.check().unwrap();
+        assert_eq!((ccs.t, simplified.t), (5, 3));
+        assert_eq!(simplified.S, vec![vec![0, 0, 0], vec![1], vec![2]]);
+        assert_eq!((simplified.q, simplified.d), (ccs.q, ccs.d));
+
+        let z = get_test_z_cubic::<Fr>(3);
+        simplified.check_relation(&z).unwrap();
+        let mut bad_z = z.clone();
+        bad_z[2] += Fr::one();
+        assert!(simplified.check_relation(&bad_z).is_err());
+
+        // unreferenced matrices are dropped, and the order of the row entries does not matter
+        let mut ccs = get_test_ccs::<Fr>();
+        ccs.M.push(ccs.M[0].clone());
+        ccs.M[3].coeffs[2].reverse();
+        ccs.M.push(ccs.M[2].clone());
+        ccs.t = 5;
+        ccs.S = vec![vec![3, 1], vec![2]];
+        ccs.check().unwrap();
+        let simplified = ccs.simplify();
+        assert_eq!(simplified.t, 3);
+        assert_eq!(simplified.M[0], ccs.M[0]);
+        assert_eq!(simplified.S, vec![vec![0, 1], vec![2]]);
+        simplified.check_relation(&get_test_z(3)).unwrap();
+
+        // a CCS without duplicates is left unchanged
+        let ccs = get_test_ccs::<Fr>();
+        assert_eq!(ccs.simplify(), ccs);
     }
 }
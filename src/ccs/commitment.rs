@@ -0,0 +1,53 @@
+use ark_ec::CurveGroup;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use core::fmt::Debug;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CommitmentError {
+    #[error("Cannot commit to a vector of length {got} with parameters of length {expected}")]
+    InvalidLength { expected: usize, got: usize },
+    #[error("The range {start}..{end} is out of the parameters of length {len}")]
+    InvalidRange {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    #[error("Deserialization error: {0}")]
+    Deserialization(#[from] SerializationError),
+    #[error("{got} bytes left after deserializing the commitment")]
+    TrailingBytes { got: usize },
+}
+
+/// The parameters of a commitment scheme, naming the scheme and the group they are for, so that
+/// functions taking the parameters, such as [`crate::ccs::ccs::CCS::to_lcccs`], infer the scheme
+/// out of them instead of having it spelled out
+pub trait CommitmentParams: Clone + Debug {
+    type Curve: CurveGroup;
+    type Scheme: CommitmentScheme<Self::Curve, Params = Self>;
+}
+
+/// A homomorphic vector commitment scheme.
+///
+/// Multifolding only needs to commit to witnesses and to linearly combine the resulting
+/// commitments, so any scheme providing these operations (Pedersen, IPA, KZG, ...) can be used to
+/// commit to the CCS witnesses.
+pub trait CommitmentScheme<C: CurveGroup> {
+    type Params: Clone + Debug;
+    // commitments are absorbed into the Fiat-Shamir transcript and sent along with the instances,
+    // so they need to be serializable
+    type Commitment: Clone + Debug + Eq + PartialEq + CanonicalSerialize + CanonicalDeserialize;
+
+    /// Commit to the vector `v` using the randomness `r`
+    fn commit(
+        params: &Self::Params,
+        v: &[C::ScalarField],
+        r: &C::ScalarField,
+    ) -> Result<Self::Commitment, CommitmentError>;
+
+    /// Homomorphically add two commitments
+    fn add(a: &Self::Commitment, b: &Self::Commitment) -> Self::Commitment;
+
+    /// Homomorphically multiply a commitment by a scalar
+    fn scalar_mul(a: &Self::Commitment, s: &C::ScalarField) -> Self::Commitment;
+}
@@ -0,0 +1,146 @@
+use ark_ec::CurveGroup;
+use core::ops::Range;
+
+use crate::ccs::cccs::CCCS;
+use crate::ccs::ccs::CCSError;
+use crate::ccs::commitment::CommitmentScheme;
+use crate::ccs::lcccs::LCCCS;
+
+/// Named segments of the public input/output x of a CCS, such as a "state_root" followed by a
+/// "nonce", so that applications don't need to keep track of the offsets into x themselves
+///
+/// The layout is optional and only used to read the instances: the folding does not depend on it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IoLayout {
+    segments: Vec<(String, Range<usize>)>,
+}
+
+impl IoLayout {
+    /// Lay out consecutive segments of the given names and lengths, starting at x[0]. Return
+    /// `CCSError::DuplicateIoSegment` if a name appears twice.
+    pub fn new(segments: &[(&str, usize)]) -> Result<Self, CCSError> {
+        let mut layout = IoLayout { segments: vec![] };
+        for (name, len) in segments {
+            if layout.segments.iter().any(|(other, _)| other == name) {
+                return Err(CCSError::DuplicateIoSegment {
+                    name: name.to_string(),
+                });
+            }
+            let start = layout.len();
+            layout.segments.push((name.to_string(), start..start + len));
+        }
+        Ok(layout)
+    }
+
+    /// Return the length of the x vectors of this layout, which has to be l
+    pub fn len(&self) -> usize {
+        self.segments.last().map_or(0, |(_, range)| range.end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the range of the segment in x
+    pub fn range(&self, name: &str) -> Result<Range<usize>, CCSError> {
+        self.segments
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, range)| range.clone())
+            .ok_or_else(|| CCSError::UnknownIoSegment {
+                name: name.to_string(),
+            })
+    }
+
+    /// Return the segment of x, or an error if x is not of the length of the layout
+    pub fn get<'a, F>(&self, x: &'a [F], name: &str) -> Result<&'a [F], CCSError> {
+        if x.len() != self.len() {
+            return Err(CCSError::InvalidPublicInputLength {
+                expected: self.len(),
+                got: x.len(),
+            });
+        }
+        Ok(&x[self.range(name)?])
+    }
+}
+
+impl<C: CurveGroup, CS: CommitmentScheme<C>> LCCCS<C, CS> {
+    /// Return the segment of the public input/output x of the instance
+    pub fn get_io(&self, layout: &IoLayout, name: &str) -> Result<&[C::ScalarField], CCSError> {
+        layout.get(&self.x, name)
+    }
+}
+
+impl<C: CurveGroup, CS: CommitmentScheme<C>> CCCS<C, CS> {
+    /// Return the segment of the public input/output x of the instance
+    pub fn get_io(&self, layout: &IoLayout, name: &str) -> Result<&[C::ScalarField], CCSError> {
+        layout.get(&self.x, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::{Fr, G1Projective};
+    use ark_std::{test_rng, UniformRand};
+
+    use crate::ccs::ccs::test::{get_random_ccs, get_random_z};
+    use crate::ccs::pedersen::Pedersen;
+    use crate::multifolding::{FoldProof, Multifolding};
+
+    #[test]
+    fn test_io_layout() -> () {
+        let mut rng = test_rng();
+
+        let layout = IoLayout::new(&[("state_root", 1), ("nonce", 2)]).unwrap();
+        assert_eq!(layout.len(), 3);
+        assert_eq!(layout.range("nonce").unwrap(), 1..3);
+        assert!(matches!(
+            layout.range("balance"),
+            Err(CCSError::UnknownIoSegment { .. })
+        ));
+        assert!(matches!(
+            IoLayout::new(&[("nonce", 1), ("nonce", 2)]),
+            Err(CCSError::DuplicateIoSegment { .. })
+        ));
+
+        let ccs = get_random_ccs::<Fr, _>(&mut rng, layout.len(), 4);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let x: Vec<Fr> = (0..ccs.l).map(|_| Fr::rand(&mut rng)).collect();
+        let (lcccs, _) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_random_z(&ccs, &x))
+            .unwrap();
+        let x_2: Vec<Fr> = (0..ccs.l).map(|_| Fr::rand(&mut rng)).collect();
+        let (cccs, _) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_random_z(&ccs, &x_2))
+            .unwrap();
+        assert_eq!(lcccs.get_io(&layout, "state_root").unwrap(), &x[..1]);
+        assert_eq!(cccs.get_io(&layout, "nonce").unwrap(), &x_2[1..]);
+
+        // the segments of the folded instance are the folded segments
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let rho = Fr::rand(&mut rng);
+        let fold_proof = FoldProof {
+            sigmas: vec![vec![Fr::rand(&mut rng); ccs.t]],
+            thetas: vec![vec![Fr::rand(&mut rng); ccs.t]],
+            r_x_prime,
+        };
+        let folded =
+            Multifolding::<G1Projective>::fold(&[lcccs], &[cccs], &fold_proof, rho).unwrap();
+        assert_eq!(
+            folded.get_io(&layout, "nonce").unwrap(),
+            &[x[1] + rho * x_2[1], x[2] + rho * x_2[2]]
+        );
+
+        // the layout must cover the whole x
+        let short_layout = IoLayout::new(&[("state_root", 1)]).unwrap();
+        assert!(matches!(
+            folded.get_io(&short_layout, "state_root"),
+            Err(CCSError::InvalidPublicInputLength {
+                expected: 1,
+                got: 3
+            })
+        ));
+    }
+}
@@ -1,109 +1,528 @@
+use alloc::sync::Arc;
 use ark_ec::CurveGroup;
+use ark_ff::{Field, PrimeField};
 use ark_poly::DenseMultilinearExtension;
-use ark_std::{One};
-use std::sync::Arc;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{One, Zero};
+use core::fmt;
 
-use ark_std::{rand::Rng, UniformRand};
+use ark_std::{cfg_iter, rand::Rng, UniformRand};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::ccs::cccs::Witness;
+use crate::ccs::cccs::{Witness, CCCS};
 use crate::ccs::ccs::{CCSError, CCS};
-use crate::ccs::util::{compute_all_sum_Mz_evals, compute_sum_Mz};
+use crate::ccs::util::{read_compressed, PreparedWitness};
+use crate::util::mle::{eval_mle_at, vec_to_mle};
+use crate::util::vec::mat_vec_mul_sparse;
 
-use crate::ccs::pedersen::{Commitment, Params as PedersenParams, Pedersen};
+use crate::ccs::commitment::{CommitmentParams, CommitmentScheme};
+use crate::ccs::pedersen::Pedersen;
 use crate::espresso::virtual_polynomial::VirtualPolynomial;
-use crate::util::mle::matrix_to_mle;
-use crate::util::mle::vec_to_mle;
+use crate::multifolding::{FoldProof, Multifolding};
 
 /// Linearized Committed CCS instance
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct LCCCS<C: CurveGroup> {
-    // Underlying CCS structure
-    pub ccs: CCS<C>,
+///
+/// The CCS is behind an `Arc`, so that cloning and folding instances shares the matrices instead
+/// of copying them. The derived `PartialEq` compares the whole structure, including the matrices
+/// of the CCS when the instances do not point to the same one. Use [`LCCCS::eq_instance`] to
+/// compare instances known to share the same CCS.
+///
+/// The `Debug` and `Display` outputs only give the dimensions of the CCS, so that instances can be
+/// logged in folding loops. Use [`LCCCS::debug_verbose`] to also print the matrices.
+#[derive(Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct LCCCS<C: CurveGroup, CS: CommitmentScheme<C> = Pedersen<C>> {
+    /// Underlying CCS structure, shared with the instances it is folded with
+    pub ccs: Arc<CCS<C::ScalarField>>,
 
     // TODO: Further improve the abstractions here. We should not need so many public fields
+    /// Commitment to witness
+    pub C: CS::Commitment,
+    /// Relaxation factor of z for folded LCCCS
+    pub u: C::ScalarField,
+    /// Public input/output
+    pub x: Vec<C::ScalarField>,
+    /// Random evaluation point for the v_i
+    pub r_x: Vec<C::ScalarField>,
+    /// Vector of v_i
+    pub v: Vec<C::ScalarField>,
+}
 
-    // Commitment to witness
-    pub C: Commitment<C>,
-    // Relaxation factor of z for folded LCCCS
+/// The instance values of an LCCCS, without its CCS structure
+///
+/// The CCS is fixed across the folding steps, so this is the part of an LCCCS worth sending: its
+/// size does not depend on the number of entries of the matrices. Use [`LCCCS::with_ccs`] to
+/// rebuild the LCCCS out of it and the shared CCS.
+#[derive(Debug, Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct LCCCSInstance<C: CurveGroup, CS: CommitmentScheme<C> = Pedersen<C>> {
+    pub C: CS::Commitment,
     pub u: C::ScalarField,
-    // Public input/output
     pub x: Vec<C::ScalarField>,
-    // Random evaluation point for the v_i
     pub r_x: Vec<C::ScalarField>,
-    // Vector of v_i
     pub v: Vec<C::ScalarField>,
 }
 
-impl<C: CurveGroup> CCS<C> {
+impl<C: CurveGroup, CS: CommitmentScheme<C>> fmt::Debug for LCCCS<C, CS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LCCCS")
+            .field("ccs", &format_args!("CCS {{ {} }}", self.ccs.dimensions()))
+            .field("C", &self.C)
+            .field("u", &self.u)
+            .field("x", &self.x)
+            .field("r_x", &self.r_x)
+            .field("v", &self.v)
+            .finish()
+    }
+}
+
+impl<C: CurveGroup, CS: CommitmentScheme<C>> fmt::Display for LCCCS<C, CS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LCCCS of a CCS with {}: C = {:?}, u = {}, |x| = {}, |r_x| = {}, |v| = {}",
+            self.ccs.dimensions(),
+            self.C,
+            self.u,
+            self.x.len(),
+            self.r_x.len(),
+            self.v.len()
+        )
+    }
+}
+
+impl<F: PrimeField> CCS<F> {
     /// Compute v_j values of the linearized committed CCS form
     /// Given `r`, compute:  \sum_{y \in {0,1}^s'} M_j(r, y) * z(y)
-    fn compute_v_j(&self, z: &[C::ScalarField], r: &[C::ScalarField]) -> Vec<C::ScalarField> {
-        compute_all_sum_Mz_evals(&self.M, &z.to_vec(), r, self.s_prime)
+    ///
+    /// r may be over an extension field E of F, giving v_j values over E.
+    pub(crate) fn compute_v_j<E: Field<BasePrimeField = F>>(
+        &self,
+        z: &[F],
+        r: &[E],
+    ) -> Result<Vec<E>, CCSError> {
+        if r.len() != self.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: self.s,
+                got: r.len(),
+            });
+        }
+        PreparedWitness::from_Mz_vectors(self.compute_Mz_vectors(z)?, self.s).evaluate(r)
     }
 
-    pub fn to_lcccs<R: Rng>(
+    /// Commit to the witness part of z = (1, x, w) and compute the v_j values at a random r_x,
+    /// returning the LCCCS instance and its witness. Return `CCSError::InvalidWitnessLength` if z
+    /// is not of length n.
+    ///
+    /// The r_x is chosen by the prover and nothing proves that z satisfies the CCS, so this is
+    /// only meant for tests and benchmarks: use [`CCCS::linearize`] to get a proven LCCCS.
+    pub fn to_lcccs<R: Rng, C: CurveGroup<ScalarField = F>, P: CommitmentParams<Curve = C>>(
         &self,
         rng: &mut R,
-        pedersen_params: &PedersenParams<C>,
-        z: &[C::ScalarField],
-    ) -> (LCCCS<C>, Witness<C::ScalarField>) {
-        let w: Vec<C::ScalarField> = z[(1 + self.l)..].to_vec();
-        let r_w = C::ScalarField::rand(rng);
-        let C = Pedersen::commit(pedersen_params, &w, &r_w);
+        cs_params: &P,
+        z: &[F],
+    ) -> Result<(LCCCS<C, P::Scheme>, Witness<F>), CCSError> {
+        let r_w = F::rand(rng);
+        let r_x: Vec<F> = (0..self.s).map(|_| F::rand(rng)).collect();
+        self.to_lcccs_with_randomness(cs_params, z, r_w, r_x)
+    }
+
+    /// Same as [`Self::to_lcccs`], with the randomness r_w of the commitment and the point r_x
+    /// given by the caller instead of sampled, e.g. to derive them from a transcript for
+    /// deterministic proving.
+    pub fn to_lcccs_with_randomness<
+        C: CurveGroup<ScalarField = F>,
+        P: CommitmentParams<Curve = C>,
+    >(
+        &self,
+        cs_params: &P,
+        z: &[F],
+        r_w: F,
+        r_x: Vec<F>,
+    ) -> Result<(LCCCS<C, P::Scheme>, Witness<F>), CCSError> {
+        self.check_z_length(z)?;
+
+        let C = <P::Scheme as CommitmentScheme<C>>::commit(cs_params, &z[(1 + self.l)..], &r_w)?;
+        self.to_lcccs_with_commitment::<C, P::Scheme>(z, C, r_w, r_x)
+    }
+
+    /// Build the LCCCS instance of z = (1, x, w) at r_x out of an already computed commitment C
+    /// to w with randomness r_w, e.g. the commitment of the CCCS instance being linearized, to
+    /// save the MSM of [`Self::to_lcccs`].
+    ///
+    /// The commitment is trusted: use [`LCCCS::verify_fold_commitment`] to check that it is the
+    /// commitment to w. Return an error if z is not of length n or r_x not of length s.
+    pub fn to_lcccs_with_commitment<C: CurveGroup<ScalarField = F>, CS: CommitmentScheme<C>>(
+        &self,
+        z: &[F],
+        C: CS::Commitment,
+        r_w: F,
+        r_x: Vec<F>,
+    ) -> Result<(LCCCS<C, CS>, Witness<F>), CCSError> {
+        self.check_z_length(z)?;
 
-        let r_x: Vec<C::ScalarField> = (0..self.s).map(|_| C::ScalarField::rand(rng)).collect();
-        let v = self.compute_v_j(z, &r_x);
+        let w: Vec<F> = z[(1 + self.l)..].to_vec();
+        let v = self.compute_v_j(z, &r_x)?;
 
-        (
-            LCCCS::<C> {
-                ccs: self.clone(),
+        Ok((
+            LCCCS::<C, CS> {
+                ccs: Arc::new(self.clone()),
                 C,
-                u: C::ScalarField::one(),
+                u: F::one(),
                 x: z[1..(1 + self.l)].to_vec(),
                 r_x,
                 v,
             },
-            Witness::<C::ScalarField> { w, r_w },
-        )
+            Witness::<F> { w, r_w },
+        ))
     }
 }
 
-impl<C: CurveGroup> LCCCS<C> {
-    /// Compute all L_j(x) polynomials
-    pub fn compute_Ls(&self, z: &Vec<C::ScalarField>) -> Vec<VirtualPolynomial<C::ScalarField>> {
-        let z_mle = vec_to_mle(self.ccs.s_prime, z);
-        // Convert all matrices to MLE
-        let M_x_y_mle: Vec<DenseMultilinearExtension<C::ScalarField>> =
-            self.ccs.M.clone().into_iter().map(matrix_to_mle).collect();
+impl<C: CurveGroup, CS: CommitmentScheme<C>> LCCCS<C, CS> {
+    /// The `Debug` output of the whole instance, including the matrices of its CCS
+    pub fn debug_verbose(&self) -> String {
+        format!(
+            "LCCCS {{ ccs: {:?}, C: {:?}, u: {:?}, x: {:?}, r_x: {:?}, v: {:?} }}",
+            self.ccs, self.C, self.u, self.x, self.r_x, self.v
+        )
+    }
+
+    /// Compare the instance values C, u, x, r_x and v, assuming that both instances share the same
+    /// CCS structure, which is not compared
+    pub fn eq_instance(&self, other: &Self) -> bool {
+        self.C == other.C
+            && self.u == other.u
+            && self.x == other.x
+            && self.r_x == other.r_x
+            && self.v == other.v
+    }
+
+    /// Return the z = (u, x, w) vector of the instance with the given witness
+    pub fn z(&self, w: &Witness<C::ScalarField>) -> Result<Vec<C::ScalarField>, CCSError> {
+        self.ccs.assemble_z(self.u, &self.x, &w.w)
+    }
+
+    /// Compute all L_j(x) polynomials, L_j(x) = eq(r_x, x) * \sum_{y \in {0,1}^s'} M_j(x, y) * z(y)
+    ///
+    /// Summing L_j(x) over the boolean hypercube gives v_j. Since L_j(x) is the product of two
+    /// multilinear polynomials, it is returned as a VirtualPolynomial of degree 2, ready to be fed
+    /// to a sumcheck.
+    ///
+    /// The M_j * z products of the t matrices are independent of each other, so with the
+    /// `parallel` feature they are computed in parallel. The polynomials are returned in the
+    /// order of the matrices. Return an error if z does not have length n.
+    ///
+    /// ```
+    /// # #![allow(non_snake_case)]
+    /// use ark_bls12_381::{Fr, G1Projective};
+    /// use ark_std::{test_rng, Zero};
+    /// use multifolding_poc::ccs::ccs::CCS;
+    /// use multifolding_poc::ccs::pedersen::Pedersen;
+    /// use multifolding_poc::util::hypercube::BooleanHypercube;
+    /// use multifolding_poc::util::vec::{to_F_matrix, to_F_vec, SparseMatrix};
+    ///
+    /// // x * x = y, padded with an empty constraint
+    /// let A = SparseMatrix::from_dense(&to_F_matrix::<Fr>(vec![vec![0, 1, 0], vec![0, 0, 0]]));
+    /// let C = SparseMatrix::from_dense(&to_F_matrix::<Fr>(vec![vec![0, 0, 1], vec![0, 0, 0]]));
+    /// let ccs = CCS::from_r1cs(A.clone(), A, C, 1).unwrap();
+    /// let z: Vec<Fr> = to_F_vec(vec![1, 3, 9]);
+    ///
+    /// let mut rng = test_rng();
+    /// let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+    /// let (lcccs, _) = ccs
+    ///     .to_lcccs(&mut rng, &pedersen_params, &z)
+    ///     .unwrap();
+    ///
+    /// for (L_j, v_j) in lcccs.compute_Ls(&z).unwrap().iter().zip(lcccs.v.iter()) {
+    ///     let sum_L_j = BooleanHypercube::new(ccs.s)
+    ///         .map(|x| L_j.evaluate(&x).unwrap())
+    ///         .fold(Fr::zero(), |acc, e| acc + e);
+    ///     assert_eq!(&sum_L_j, v_j);
+    /// }
+    /// ```
+    pub fn compute_Ls(
+        &self,
+        z: &Vec<C::ScalarField>,
+    ) -> Result<Vec<VirtualPolynomial<C::ScalarField>>, CCSError> {
+        // the virtual polynomials keep raw pointers to their MLEs, so they can't be sent across
+        // threads: only the M_j * z products and their MLEs are computed in parallel
+        let vec_Mz = self.ccs.compute_Mz_vectors(z)?;
+        let sum_Mzs: Vec<DenseMultilinearExtension<C::ScalarField>> = cfg_iter!(vec_Mz)
+            .map(|Mz_j| vec_to_mle(self.ccs.s, Mz_j))
+            .collect();
+
+        Ok(sum_Mzs
+            .into_iter()
+            .map(|sum_Mz| {
+                let sum_Mz_virtual =
+                    VirtualPolynomial::new_from_mle(&Arc::new(sum_Mz), C::ScalarField::one());
+                sum_Mz_virtual.build_f_hat(&self.r_x).unwrap()
+            })
+            .collect())
+    }
+
+    /// View the instance as a CCCS instance, with the same commitment and public input/output,
+    /// dropping u, r_x and v
+    ///
+    /// The z vector of a CCCS instance is (1, x, w) while the one of an LCCCS instance is
+    /// (u, x, w), so the CCCS relation only holds for the same witness if u == 1. Use
+    /// [`Self::try_to_cccs`] to check it.
+    pub fn to_cccs_view(&self) -> CCCS<C, CS> {
+        CCCS::<C, CS> {
+            ccs: self.ccs.clone(),
+            C: self.C.clone(),
+            x: self.x.clone(),
+        }
+    }
+
+    /// View the instance as a CCCS instance, or return `CCSError::InvalidRelaxationFactor` if
+    /// u != 1
+    pub fn try_to_cccs(&self) -> Result<CCCS<C, CS>, CCSError> {
+        if !self.u.is_one() {
+            return Err(CCSError::InvalidRelaxationFactor);
+        }
+        Ok(self.to_cccs_view())
+    }
+
+    /// Re-randomize the commitment to the witness, returning the blinded instance and its witness
+    ///
+    /// The randomness r_w is shifted by a random delta, and since the commitment is homomorphic,
+    /// C is updated by adding a commitment to the zero vector with randomness delta. Notice that
+    /// this only hides the commitment: u, x, r_x and v are left untouched.
+    pub fn blind<R: Rng>(
+        &self,
+        rng: &mut R,
+        cs_params: &CS::Params,
+        w: &Witness<C::ScalarField>,
+    ) -> Result<(Self, Witness<C::ScalarField>), CCSError> {
+        let delta = C::ScalarField::rand(rng);
+        let C_delta = CS::commit(cs_params, &vec![C::ScalarField::zero(); w.w.len()], &delta)?;
+
+        let mut blinded = self.clone();
+        blinded.C = CS::add(&self.C, &C_delta);
+        Ok((
+            blinded,
+            Witness {
+                w: w.w.clone(),
+                r_w: w.r_w + delta,
+            },
+        ))
+    }
+
+    /// Serialize the instance without its CCS structure, as the compressed encodings of C, u, x,
+    /// r_x and v in that order. The lengths of x, r_x and v are not encoded, since they are given
+    /// by the CCS.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.C.serialize_compressed(&mut bytes).unwrap();
+        self.u.serialize_compressed(&mut bytes).unwrap();
+        for e in self.x.iter().chain(self.r_x.iter()).chain(self.v.iter()) {
+            e.serialize_compressed(&mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    /// Return the instance values C, u, x, r_x and v, dropping the CCS structure
+    pub fn instance(&self) -> LCCCSInstance<C, CS> {
+        LCCCSInstance {
+            C: self.C.clone(),
+            u: self.u,
+            x: self.x.clone(),
+            r_x: self.r_x.clone(),
+            v: self.v.clone(),
+        }
+    }
+
+    /// Rebuild the LCCCS out of its instance values and its shared CCS structure, checking that
+    /// the lengths of x, r_x and v match the CCS
+    pub fn with_ccs(
+        instance: LCCCSInstance<C, CS>,
+        ccs: &Arc<CCS<C::ScalarField>>,
+    ) -> Result<Self, CCSError> {
+        if instance.x.len() != ccs.l {
+            return Err(CCSError::InvalidPublicInputLength {
+                expected: ccs.l,
+                got: instance.x.len(),
+            });
+        }
+        if instance.r_x.len() != ccs.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: ccs.s,
+                got: instance.r_x.len(),
+            });
+        }
+        if instance.v.len() != ccs.t {
+            return Err(CCSError::VLengthMismatch {
+                t: ccs.t,
+                got: instance.v.len(),
+            });
+        }
+        Ok(LCCCS {
+            ccs: ccs.clone(),
+            C: instance.C,
+            u: instance.u,
+            x: instance.x,
+            r_x: instance.r_x,
+            v: instance.v,
+        })
+    }
+
+    /// Deserialize an instance of the given CCS out of bytes produced by [`Self::to_bytes`].
+    ///
+    /// This never panics on malformed bytes: it returns an error if the bytes are too short, if
+    /// an encoding is not a valid field element or point, or if there are bytes left over.
+    pub fn from_bytes(ccs: &Arc<CCS<C::ScalarField>>, bytes: &[u8]) -> Result<Self, CCSError> {
+        let mut bytes = bytes;
+        let C = CS::Commitment::deserialize_compressed(&mut bytes)?;
+        let u = C::ScalarField::deserialize_compressed(&mut bytes)?;
+        let x = read_compressed(&mut bytes, ccs.l)?;
+        let r_x = read_compressed(&mut bytes, ccs.s)?;
+        let v = read_compressed(&mut bytes, ccs.t)?;
+        if !bytes.is_empty() {
+            return Err(CCSError::TrailingBytes { got: bytes.len() });
+        }
+        Ok(LCCCS {
+            ccs: ccs.clone(),
+            C,
+            u,
+            x,
+            r_x,
+            v,
+        })
+    }
 
-        let mut vec_L_j_x = Vec::with_capacity(self.ccs.t);
-        for M_j in M_x_y_mle {
-            let sum_Mz = compute_sum_Mz(M_j, &z_mle, self.ccs.s_prime);
-            let sum_Mz_virtual =
-                VirtualPolynomial::new_from_mle(&Arc::new(sum_Mz.clone()), C::ScalarField::one());
-            let L_j_x = sum_Mz_virtual.build_f_hat(&self.r_x).unwrap();
-            vec_L_j_x.push(L_j_x);
+    /// Check that C is the commitment to the witness, e.g. to a folded witness. This is only the
+    /// commitment part of [`Self::check_relation`], so it is a cheap check that the witness and
+    /// the instance were folded consistently, which does not evaluate the v_j values.
+    pub fn verify_fold_commitment(
+        &self,
+        cs_params: &CS::Params,
+        folded_witness: &Witness<C::ScalarField>,
+    ) -> Result<(), CCSError> {
+        // Notice that this is not verifying a commitment opening, but checking that the
+        // Commmitment comes from committing to the witness.
+        if self.C != CS::commit(cs_params, &folded_witness.w, &folded_witness.r_w)? {
+            return Err(CCSError::CommitmentMismatch);
         }
+        Ok(())
+    }
 
-        vec_L_j_x
+    /// Fold this LCCCS instance with a CCCS instance, weighting them by 1 and rho, out of the
+    /// sigmas and thetas of the two instances at r_x'. This is the instance-only fold of the
+    /// verifier: it never touches the witnesses, which the prover folds separately with
+    /// [`Multifolding::fold_witness`], and the commitment to the folded witness is the folded C.
+    /// See [`Multifolding::fold`] for folding more instances at once.
+    pub fn fold_verify(
+        &self,
+        cccs: &CCCS<C, CS>,
+        sigmas: &[C::ScalarField],
+        thetas: &[C::ScalarField],
+        r_x_prime: Vec<C::ScalarField>,
+        rho: C::ScalarField,
+    ) -> Result<Self, CCSError> {
+        let fold_proof = FoldProof::<C> {
+            sigmas: vec![sigmas.to_vec()],
+            thetas: vec![thetas.to_vec()],
+            r_x_prime,
+        };
+        Multifolding::<C, CS>::fold(&[self.clone()], &[cccs.clone()], &fold_proof, rho)
     }
 
     /// Perform the check of the LCCCS instance described at section 4.2
     pub fn check_relation(
         &self,
-        pedersen_params: &PedersenParams<C>,
+        cs_params: &CS::Params,
+        w: &Witness<C::ScalarField>,
+    ) -> Result<(), CCSError> {
+        let z = self.z(w)?;
+        self.check_relation_with_z(cs_params, w, &z)
+    }
+
+    /// Same as [`Self::check_relation`], with the z = (u, x, w) vector already assembled by the
+    /// caller, so that it is not rebuilt out of the witness on each check.
+    ///
+    /// z is trusted to be the z vector of the instance and w, which is only checked in debug
+    /// builds: otherwise the check is of z against the instance and of w against the commitment.
+    pub fn check_relation_with_z(
+        &self,
+        cs_params: &CS::Params,
         w: &Witness<C::ScalarField>,
+        z: &[C::ScalarField],
     ) -> Result<(), CCSError> {
-        // check that C is the commitment of w. Notice that this is not verifying a Pedersen
-        // opening, but checking that the Commmitment comes from committing to the witness.
-        assert_eq!(self.C.0, Pedersen::commit(pedersen_params, &w.w, &w.r_w).0);
+        debug_assert!(
+            self.z(w).is_ok_and(|z_w| z_w == z),
+            "z is not the (u, x, w) vector of the instance"
+        );
+
+        // check that C is the commitment of w
+        self.verify_fold_commitment(cs_params, w)?;
+
+        // there is one v_j for each matrix M_j
+        if self.v.len() != self.ccs.t {
+            return Err(CCSError::VLengthMismatch {
+                t: self.ccs.t,
+                got: self.v.len(),
+            });
+        }
 
         // check CCS relation
-        let z: Vec<C::ScalarField> = [vec![self.u], self.x.clone(), w.w.to_vec()].concat();
-        let computed_v = compute_all_sum_Mz_evals(&self.ccs.M, &z, &self.r_x, self.ccs.s_prime);
-        assert_eq!(computed_v, self.v);
+        let computed_v = self.ccs.compute_v_j(z, &self.r_x)?;
+        // report the first mismatching v_j
+        let mismatch = computed_v
+            .iter()
+            .zip(self.v.iter())
+            .position(|(computed_v_j, v_j)| computed_v_j != v_j);
+        if let Some(index) = mismatch {
+            return Err(CCSError::VEvaluationMismatch { index });
+        }
         Ok(())
     }
+
+    /// Probabilistic version of [`Self::check_relation`], which checks a random linear
+    /// combination \sum gamma^j v_j of the v_j values instead of each of them, so that the M_j z
+    /// vectors are combined into a single vector and a single MLE evaluation is done instead of
+    /// t of them. The v_j values are checked before the commitment, so garbage instances are
+    /// rejected without computing the commitment.
+    ///
+    /// A wrong v passes the check with probability at most (t - 1) / |F| over the choice of gamma.
+    /// This is useful to quickly reject bad instances in a loop, but [`Self::check_relation`]
+    /// remains the authoritative check.
+    pub fn check_relation_fast<R: Rng>(
+        &self,
+        rng: &mut R,
+        cs_params: &CS::Params,
+        w: &Witness<C::ScalarField>,
+    ) -> Result<(), CCSError> {
+        if self.v.len() != self.ccs.t {
+            return Err(CCSError::VLengthMismatch {
+                t: self.ccs.t,
+                got: self.v.len(),
+            });
+        }
+        if self.r_x.len() != self.ccs.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: self.ccs.s,
+                got: self.r_x.len(),
+            });
+        }
+        let z = self.z(w)?;
+
+        // \sum gamma^j M_j z and \sum gamma^j v_j, by Horner's rule from the last matrix
+        let gamma = C::ScalarField::rand(rng);
+        let mut combined_Mz = vec![C::ScalarField::zero(); self.ccs.m];
+        let mut combined_v = C::ScalarField::zero();
+        for (M_j, v_j) in self.ccs.M.iter().zip(self.v.iter()).rev() {
+            let Mz_j = mat_vec_mul_sparse(M_j, &z);
+            for (acc, Mz_j_i) in combined_Mz.iter_mut().zip(Mz_j.iter()) {
+                *acc = *acc * gamma + Mz_j_i;
+            }
+            combined_v = combined_v * gamma + v_j;
+        }
+        if eval_mle_at(&combined_Mz, &self.r_x) != combined_v {
+            return Err(CCSError::VCombinationMismatch);
+        }
+
+        self.verify_fold_commitment(cs_params, w)
+    }
 }
 
 #[cfg(test)]
@@ -111,7 +530,8 @@ pub mod test {
     use super::*;
     use ark_std::Zero;
 
-    use crate::ccs::ccs::test::{get_test_ccs, get_test_z};
+    use crate::ccs::ccs::test::{get_random_ccs, get_random_z, get_test_ccs, get_test_z};
+    use crate::ccs::util::compute_sum_Mz;
     use crate::util::hypercube::BooleanHypercube;
     use ark_std::test_rng;
 
@@ -122,16 +542,17 @@ pub mod test {
     fn test_lcccs_v_j() -> () {
         let mut rng = test_rng();
 
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z = get_test_z(3);
         ccs.check_relation(&z.clone()).unwrap();
 
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
         // with our test vector comming from R1CS, v should have length 3
         assert_eq!(lcccs.v.len(), 3);
 
-        let vec_L_j_x = lcccs.compute_Ls(&z);
+        let vec_L_j_x = lcccs.compute_Ls(&z).unwrap();
         assert_eq!(vec_L_j_x.len(), lcccs.v.len());
 
         for (v_i, L_j_x) in lcccs.v.into_iter().zip(vec_L_j_x) {
@@ -143,12 +564,40 @@ pub mod test {
         }
     }
 
+    /// The L_j(x) polynomials, which may be computed in parallel, are the same, and in the same
+    /// order, as the ones computed one matrix after the other
+    #[test]
+    fn test_compute_Ls_matrix_order() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_random_ccs::<Fr, _>(&mut rng, 2, 12);
+        let z = get_random_z(&ccs, &[Fr::rand(&mut rng), Fr::rand(&mut rng)]);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let vec_L_j_x = lcccs.compute_Ls(&z).unwrap();
+        assert_eq!(vec_L_j_x.len(), ccs.t);
+        for (M_j, L_j_x) in ccs.M.iter().zip(vec_L_j_x.iter()) {
+            let sum_Mz = compute_sum_Mz(M_j, &z, ccs.s);
+            let expected = VirtualPolynomial::new_from_mle(&Arc::new(sum_Mz), Fr::one())
+                .build_f_hat(&lcccs.r_x)
+                .unwrap();
+            assert_eq!(L_j_x.aux_info, expected.aux_info);
+            assert_eq!(L_j_x.products, expected.products);
+            assert_eq!(
+                L_j_x.flattened_ml_extensions,
+                expected.flattened_ml_extensions
+            );
+        }
+    }
+
     /// Given a bad z, check that the v_j should not match with the L_j(x)
     #[test]
     fn test_bad_v_j() -> () {
         let mut rng = test_rng();
 
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z = get_test_z(3);
         ccs.check_relation(&z.clone()).unwrap();
 
@@ -157,14 +606,15 @@ pub mod test {
         bad_z[3] = Fr::zero();
         assert!(ccs.check_relation(&bad_z.clone()).is_err());
 
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
         // Compute v_j with the right z
-        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z);
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
         // with our test vector comming from R1CS, v should have length 3
         assert_eq!(lcccs.v.len(), 3);
 
         // Bad compute L_j(x) with the bad z
-        let vec_L_j_x = lcccs.compute_Ls(&bad_z);
+        let vec_L_j_x = lcccs.compute_Ls(&bad_z).unwrap();
         assert_eq!(vec_L_j_x.len(), lcccs.v.len());
 
         // Make sure that the LCCCS is not satisfied given these L_j(x)
@@ -182,4 +632,502 @@ pub mod test {
 
         assert_eq!(satisfied, false);
     }
+
+    #[test]
+    fn test_lcccs_check_relation_errors() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+        lcccs.check_relation(&pedersen_params, &w).unwrap();
+
+        // a witness that does not match the commitment
+        let mut bad_w = w.clone();
+        bad_w.r_w += Fr::from(1u64);
+        assert!(matches!(
+            lcccs.check_relation(&pedersen_params, &bad_w),
+            Err(CCSError::CommitmentMismatch)
+        ));
+
+        // v values that do not match the witness
+        let mut bad_lcccs = lcccs.clone();
+        bad_lcccs.v[1] += Fr::from(1u64);
+        assert!(matches!(
+            bad_lcccs.check_relation(&pedersen_params, &w),
+            Err(CCSError::VEvaluationMismatch { index: 1 })
+        ));
+
+        // missing v values
+        let mut bad_lcccs = lcccs.clone();
+        bad_lcccs.v.pop();
+        assert!(matches!(
+            bad_lcccs.check_relation(&pedersen_params, &w),
+            Err(CCSError::VLengthMismatch { t: 3, got: 2 })
+        ));
+
+        // an r_x of the wrong length
+        let mut bad_lcccs = lcccs.clone();
+        bad_lcccs.r_x.pop();
+        assert!(matches!(
+            bad_lcccs.check_relation(&pedersen_params, &w),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 1
+            })
+        ));
+    }
+
+    /// The rng variants only sample the randomness, so giving the same randomness gives the same
+    /// instances
+    #[test]
+    fn test_to_lcccs_with_randomness() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut test_rng(), ccs.witness_len().unwrap());
+
+        let mut rng = test_rng();
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+        let (cccs, w_cccs) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let mut rng = test_rng();
+        let r_w = Fr::rand(&mut rng);
+        let r_x: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let r_w_cccs = Fr::rand(&mut rng);
+        assert_eq!(
+            ccs.to_lcccs_with_randomness(&pedersen_params, &z, r_w, r_x)
+                .unwrap(),
+            (lcccs, w)
+        );
+        assert_eq!(
+            ccs.to_cccs_with_randomness(&pedersen_params, &z, r_w_cccs)
+                .unwrap(),
+            (cccs, w_cccs)
+        );
+    }
+
+    /// Building the LCCCS out of the commitment of a CCCS gives the same instance as committing
+    /// again
+    #[test]
+    fn test_to_lcccs_with_commitment() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let r_x: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let (lcccs, w_lcccs) = ccs
+            .to_lcccs_with_commitment::<G1Projective, Pedersen<G1Projective>>(
+                &z,
+                cccs.C.clone(),
+                w.r_w,
+                r_x.clone(),
+            )
+            .unwrap();
+        assert_eq!(w_lcccs, w);
+        assert_eq!(lcccs.C, cccs.C);
+        assert_eq!(lcccs.r_x, r_x);
+        lcccs.check_relation(&pedersen_params, &w_lcccs).unwrap();
+
+        // the commitment is trusted, so a wrong one is only caught by the relation check
+        let (bad_lcccs, bad_w) = ccs
+            .to_lcccs_with_commitment::<G1Projective, Pedersen<G1Projective>>(
+                &z,
+                cccs.C.clone(),
+                w.r_w + Fr::one(),
+                r_x.clone(),
+            )
+            .unwrap();
+        assert!(matches!(
+            bad_lcccs.check_relation(&pedersen_params, &bad_w),
+            Err(CCSError::CommitmentMismatch)
+        ));
+
+        assert!(matches!(
+            ccs.to_lcccs_with_commitment::<G1Projective, Pedersen<G1Projective>>(
+                &z,
+                cccs.C,
+                w.r_w,
+                r_x[1..].to_vec(),
+            ),
+            Err(CCSError::InvalidChallengeLength { .. })
+        ));
+    }
+
+    /// The fast check agrees with the full one on satisfied instances and on instances with bad
+    /// v values or commitments
+    #[test]
+    fn test_lcccs_check_relation_fast() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_random_ccs::<Fr, _>(&mut rng, 2, 8);
+        let z = get_random_z(&ccs, &[Fr::rand(&mut rng), Fr::rand(&mut rng)]);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+        for _ in 0..5 {
+            lcccs
+                .check_relation_fast(&mut rng, &pedersen_params, &w)
+                .unwrap();
+        }
+
+        for j in 0..ccs.t {
+            let mut bad_lcccs = lcccs.clone();
+            bad_lcccs.v[j] += Fr::rand(&mut rng);
+            assert!(matches!(
+                bad_lcccs.check_relation_fast(&mut rng, &pedersen_params, &w),
+                Err(CCSError::VCombinationMismatch)
+            ));
+        }
+        // garbage v values are rejected before checking the commitment
+        let mut bad_lcccs = lcccs.clone();
+        bad_lcccs.v = (0..ccs.t).map(|_| Fr::rand(&mut rng)).collect();
+        let mut bad_w = w.clone();
+        bad_w.r_w += Fr::one();
+        assert!(matches!(
+            bad_lcccs.check_relation_fast(&mut rng, &pedersen_params, &bad_w),
+            Err(CCSError::VCombinationMismatch)
+        ));
+        // the witness w is also in z, so only change the randomness to break the commitment
+        assert!(matches!(
+            lcccs.check_relation_fast(&mut rng, &pedersen_params, &bad_w),
+            Err(CCSError::CommitmentMismatch)
+        ));
+
+        let mut bad_lcccs = lcccs.clone();
+        bad_lcccs.v.pop();
+        assert!(matches!(
+            bad_lcccs.check_relation_fast(&mut rng, &pedersen_params, &w),
+            Err(CCSError::VLengthMismatch { .. })
+        ));
+    }
+
+    /// Checking with the z vector of the instance gives the same results as rebuilding it
+    #[test]
+    fn test_lcccs_check_relation_with_z() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z = get_test_z(3);
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+        // u = 1, so the z vector of the instance is the one it was built from
+        lcccs
+            .check_relation_with_z(&pedersen_params, &w, &z)
+            .unwrap();
+
+        let mut bad_lcccs = lcccs.clone();
+        bad_lcccs.v[1] += Fr::one();
+        assert!(matches!(
+            bad_lcccs.check_relation_with_z(&pedersen_params, &w, &z),
+            Err(CCSError::VEvaluationMismatch { index: 1 })
+        ));
+        let mut bad_w = w.clone();
+        bad_w.r_w += Fr::one();
+        assert!(matches!(
+            lcccs.check_relation_with_z(&pedersen_params, &bad_w, &z),
+            Err(CCSError::CommitmentMismatch)
+        ));
+    }
+
+    /// A z vector that is not the one of the instance and witness is caught in debug builds
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "z is not the (u, x, w) vector of the instance")]
+    fn test_lcccs_check_relation_with_wrong_z() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let _ = lcccs.check_relation_with_z(&pedersen_params, &w, &get_test_z(4));
+    }
+
+    #[test]
+    fn test_lcccs_eq_instance() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+        assert!(lcccs.eq_instance(&lcccs.clone()));
+
+        // the CCS is not compared
+        let mut other_ccs = lcccs.clone();
+        Arc::make_mut(&mut other_ccs.ccs).c[0] += Fr::from(1u64);
+        assert!(lcccs.eq_instance(&other_ccs));
+        assert_ne!(lcccs, other_ccs);
+
+        let mut other = lcccs.clone();
+        other.u += Fr::from(1u64);
+        assert!(!lcccs.eq_instance(&other));
+        let mut other = lcccs.clone();
+        other.r_x[0] += Fr::from(1u64);
+        assert!(!lcccs.eq_instance(&other));
+        let mut other = lcccs.clone();
+        other.v[2] += Fr::from(1u64);
+        assert!(!lcccs.eq_instance(&other));
+    }
+
+    #[test]
+    fn test_to_lcccs_invalid_z_length() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z: Vec<Fr> = get_test_z(3);
+
+        let too_short = z[..ccs.n - 1].to_vec();
+        let too_long = [z, vec![Fr::one()]].concat();
+        for bad_z in [too_short, too_long] {
+            let result = ccs.to_lcccs(&mut rng, &pedersen_params, &bad_z);
+            assert!(matches!(
+                result,
+                Err(CCSError::InvalidWitnessLength { expected, got })
+                    if expected == ccs.n && got == bad_z.len()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_lcccs_to_cccs() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        // u == 1, so the CCCS relation holds with the same witness
+        let cccs = lcccs.try_to_cccs().unwrap();
+        assert_eq!(cccs, lcccs.to_cccs_view());
+        cccs.check_relation(&pedersen_params, &w).unwrap();
+
+        // u != 1, as in a folded instance
+        let mut relaxed_lcccs = lcccs.clone();
+        relaxed_lcccs.u += Fr::one();
+        assert!(matches!(
+            relaxed_lcccs.try_to_cccs(),
+            Err(CCSError::InvalidRelaxationFactor)
+        ));
+    }
+
+    #[test]
+    fn test_lcccs_blind() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let (blinded, blinded_w) = lcccs.blind(&mut rng, &pedersen_params, &w).unwrap();
+        assert_ne!(blinded.C, lcccs.C);
+        blinded
+            .check_relation(&pedersen_params, &blinded_w)
+            .unwrap();
+
+        // the original witness does not open the blinded commitment
+        assert!(matches!(
+            blinded.check_relation(&pedersen_params, &w),
+            Err(CCSError::CommitmentMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_lcccs_serialization() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let mut bytes = Vec::new();
+        lcccs.serialize_compressed(&mut bytes).unwrap();
+        let lcccs_d = LCCCS::<G1Projective>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(lcccs, lcccs_d);
+
+        let mut bytes = Vec::new();
+        w.serialize_compressed(&mut bytes).unwrap();
+        let w_d = Witness::<Fr>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(w, w_d);
+
+        // the deserialized instance is still satisfied by the witness
+        lcccs_d.check_relation(&pedersen_params, &w_d).unwrap();
+    }
+
+    /// The LCCCS is printed with the dimensions of its CCS instead of its matrices
+    #[test]
+    fn test_lcccs_display() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, _) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+
+        let display = lcccs.to_string();
+        assert!(display.starts_with("LCCCS of a CCS with m = 4, n = 6, l = 1, t = 3, q = 2, d = 2"));
+        assert!(display.ends_with(&format!("u = {}, |x| = 1, |r_x| = 2, |v| = 3", lcccs.u)));
+
+        // only the verbose output has the matrices
+        let debug = format!("{:?}", lcccs);
+        assert!(debug.starts_with("LCCCS { ccs: CCS { m = 4, n = 6, l = 1, t = 3, q = 2, d = 2 }"));
+        assert!(!debug.contains("coeffs"));
+        let verbose = lcccs.debug_verbose();
+        assert!(verbose.contains("coeffs"));
+        assert!(verbose.ends_with(&format!("v: {:?} }}", lcccs.v)));
+        assert!(debug.len() < verbose.len());
+    }
+
+    /// The instance values are serialized without the CCS, which for a circuit of a few dozen
+    /// gates is already orders of magnitude smaller, and rebuild the same LCCCS
+    #[test]
+    fn test_lcccs_instance() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_random_ccs::<Fr, _>(&mut rng, 1, 64);
+        let z = get_random_z(&ccs, &[Fr::rand(&mut rng)]);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let mut bytes = Vec::new();
+        lcccs.serialize_compressed(&mut bytes).unwrap();
+        let mut instance_bytes = Vec::new();
+        lcccs
+            .instance()
+            .serialize_compressed(&mut instance_bytes)
+            .unwrap();
+        assert!(instance_bytes.len() * 100 < bytes.len());
+
+        let instance =
+            LCCCSInstance::<G1Projective>::deserialize_compressed(&instance_bytes[..]).unwrap();
+        let lcccs_d = LCCCS::with_ccs(instance, &lcccs.ccs).unwrap();
+        assert_eq!(lcccs_d, lcccs);
+        // the rebuilt instance shares the CCS instead of copying it
+        assert!(Arc::ptr_eq(&lcccs_d.ccs, &lcccs.ccs));
+        lcccs_d.check_relation(&pedersen_params, &w).unwrap();
+
+        let mut bad_instance = lcccs.instance();
+        bad_instance.v.pop();
+        assert!(matches!(
+            LCCCS::with_ccs(bad_instance, &lcccs.ccs),
+            Err(CCSError::VLengthMismatch { t: 3, got: 2 })
+        ));
+        let mut bad_instance = lcccs.instance();
+        bad_instance.r_x.push(Fr::rand(&mut rng));
+        assert!(matches!(
+            LCCCS::with_ccs(bad_instance, &lcccs.ccs),
+            Err(CCSError::InvalidChallengeLength { .. })
+        ));
+    }
+
+    /// The verifier fold of the instances commits to the prover fold of the witnesses
+    #[test]
+    fn test_lcccs_fold_verify() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let z_1 = get_test_z(3);
+        let z_2 = get_test_z(4);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w_1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
+        let (cccs, w_2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
+
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let (sigmas, thetas) = Multifolding::<G1Projective>::compute_sigmas_and_thetas(
+            &ccs,
+            &[z_1],
+            &[z_2],
+            &r_x_prime,
+        )
+        .unwrap();
+        let rho = Fr::rand(&mut rng);
+        let folded = lcccs
+            .fold_verify(&cccs, &sigmas[0], &thetas[0], r_x_prime.clone(), rho)
+            .unwrap();
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime,
+        };
+        assert_eq!(
+            folded,
+            Multifolding::fold(&[lcccs], &[cccs], &fold_proof, rho).unwrap()
+        );
+
+        let w_folded = Multifolding::<G1Projective>::fold_witness(&[w_1], &[w_2], rho);
+        assert_eq!(
+            folded.C,
+            Pedersen::commit(&pedersen_params, &w_folded.w, &w_folded.r_w).unwrap()
+        );
+        folded.check_relation(&pedersen_params, &w_folded).unwrap();
+    }
+
+    /// Deserializing arbitrary bytes returns errors instead of panicking
+    #[test]
+    fn test_lcccs_from_bytes() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z).unwrap();
+
+        let bytes = lcccs.to_bytes();
+        // C is 48 bytes, and u, x, r_x and v are 1 + 1 + 2 + 3 field elements of 32 bytes
+        assert_eq!(bytes.len(), 48 + 7 * 32);
+        let lcccs_d = LCCCS::<G1Projective>::from_bytes(&lcccs.ccs, &bytes).unwrap();
+        assert_eq!(lcccs_d, lcccs);
+        lcccs_d.check_relation(&pedersen_params, &w).unwrap();
+
+        // truncated bytes
+        for len in 0..bytes.len() {
+            assert!(matches!(
+                LCCCS::<G1Projective>::from_bytes(&lcccs.ccs, &bytes[..len]),
+                Err(CCSError::Deserialization(_))
+            ));
+        }
+        // trailing bytes
+        let long_bytes = [bytes.clone(), vec![0u8; 3]].concat();
+        assert!(matches!(
+            LCCCS::<G1Projective>::from_bytes(&lcccs.ccs, &long_bytes),
+            Err(CCSError::TrailingBytes { got: 3 })
+        ));
+        // a field element that is not reduced
+        let mut bad_bytes = bytes.clone();
+        bad_bytes[48..80].copy_from_slice(&[0xff; 32]);
+        assert!(LCCCS::<G1Projective>::from_bytes(&lcccs.ccs, &bad_bytes).is_err());
+        // random bytes either fail to deserialize or give an instance that is not satisfied
+        for _ in 0..10 {
+            let random_bytes: Vec<u8> = (0..bytes.len()).map(|_| u8::rand(&mut rng)).collect();
+            if let Ok(lcccs_r) = LCCCS::<G1Projective>::from_bytes(&lcccs.ccs, &random_bytes) {
+                assert!(lcccs_r.check_relation(&pedersen_params, &w).is_err());
+            }
+        }
+    }
 }
@@ -1,6 +1,8 @@
 pub mod cccs;
 #[allow(clippy::module_inception)]
 pub mod ccs;
+pub mod commitment;
+pub mod io;
 pub mod lcccs;
 pub mod pedersen;
 pub mod util;
@@ -1,11 +1,22 @@
+use ark_bls12_381::G1Projective;
 use ark_ec::CurveGroup;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
+use crate::ccs::commitment::{CommitmentError, CommitmentParams, CommitmentScheme};
 use crate::util::vec::{vec_add, vec_scalar_mul};
 use transcript::IOPTranscript;
 
-use ark_std::{rand::Rng, UniformRand};
+use ark_std::{cfg_iter, rand::Rng, rand::SeedableRng, UniformRand, Zero};
+use rand_chacha::ChaCha20Rng;
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use sha2::{Digest, Sha256};
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+use core::ops::{Add, Mul, Range};
+
+/// Number of entries of v that commit_streaming collects before running an MSM over them
+pub const STREAMING_CHUNK_SIZE: usize = 1 << 12;
 
 #[derive(Clone, Debug)]
 pub struct Proof<C: CurveGroup> {
@@ -14,53 +25,395 @@ pub struct Proof<C: CurveGroup> {
     ru_: C::ScalarField,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Params<C: CurveGroup> {
     h: C,
     pub generators: Vec<C::Affine>, // Affine for the MSM
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct Commitment<C: CurveGroup>(pub C);
 
-#[derive(Clone, Debug)]
+/// A consecutive part of a committed vector with its own randomness, see
+/// [`Pedersen::commit_segmented`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment<'a, F> {
+    pub values: &'a [F],
+    pub r: F,
+}
+
+impl<C: CurveGroup> Commitment<C> {
+    /// Encode the commitment as its compressed point, which only keeps the x coordinate and the
+    /// sign of y
+    pub fn to_bytes_compressed(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Encode the commitment as its uncompressed point, with both coordinates, which is about
+    /// twice as large but faster to decode
+    pub fn to_bytes_uncompressed(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.serialize_uncompressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Decode a commitment encoded by [`Self::to_bytes_compressed`]. The point is checked to be
+    /// on the curve and in the prime order subgroup, and the bytes must hold nothing else.
+    pub fn from_bytes_compressed(mut bytes: &[u8]) -> Result<Self, CommitmentError> {
+        let cm = Self::deserialize_compressed(&mut bytes)?;
+        Self::check_no_trailing_bytes(bytes)?;
+        Ok(cm)
+    }
+
+    /// Decode a commitment encoded by [`Self::to_bytes_uncompressed`]. The point is checked to be
+    /// on the curve and in the prime order subgroup, and the bytes must hold nothing else.
+    pub fn from_bytes_uncompressed(mut bytes: &[u8]) -> Result<Self, CommitmentError> {
+        let cm = Self::deserialize_uncompressed(&mut bytes)?;
+        Self::check_no_trailing_bytes(bytes)?;
+        Ok(cm)
+    }
+
+    fn check_no_trailing_bytes(rest: &[u8]) -> Result<(), CommitmentError> {
+        if !rest.is_empty() {
+            return Err(CommitmentError::TrailingBytes { got: rest.len() });
+        }
+        Ok(())
+    }
+}
+
+// Pedersen commitments are homomorphic, so that they can be folded as `C_1 + C_2 * rho` without
+// reaching into the group element
+impl<C: CurveGroup> Add for Commitment<C> {
+    type Output = Commitment<C>;
+
+    fn add(self, other: Commitment<C>) -> Commitment<C> {
+        Commitment(self.0 + other.0)
+    }
+}
+
+impl<'a, C: CurveGroup> Add<&'a Commitment<C>> for &'a Commitment<C> {
+    type Output = Commitment<C>;
+
+    fn add(self, other: &'a Commitment<C>) -> Commitment<C> {
+        Commitment(self.0 + other.0)
+    }
+}
+
+impl<C: CurveGroup> Mul<C::ScalarField> for Commitment<C> {
+    type Output = Commitment<C>;
+
+    fn mul(self, s: C::ScalarField) -> Commitment<C> {
+        Commitment(self.0 * s)
+    }
+}
+
+impl<'a, C: CurveGroup> Mul<&'a C::ScalarField> for &'a Commitment<C> {
+    type Output = Commitment<C>;
+
+    fn mul(self, s: &'a C::ScalarField) -> Commitment<C> {
+        Commitment(self.0 * s)
+    }
+}
+
+/// Pedersen commitments over the group C, whose scalar field is the field of the committed
+/// vectors, so that the curve and the scalar field always go together
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Pedersen<C: CurveGroup> {
     _c: PhantomData<C>,
 }
 
+/// Pedersen commitments over the G1 group of BLS12-381
+pub type Bls12Pedersen = Pedersen<G1Projective>;
+/// Pedersen parameters over the G1 group of BLS12-381
+pub type Bls12Params = Params<G1Projective>;
+/// Pedersen commitment over the G1 group of BLS12-381
+pub type Bls12Commitment = Commitment<G1Projective>;
+
 impl<C: CurveGroup> Pedersen<C> {
+    /// Sample h and the generators as random points, picking random x coordinates until they
+    /// land on the curve, so that no discrete logarithm between them is ever computed
     pub fn new_params<R: Rng>(rng: &mut R, max: usize) -> Params<C> {
-        let h_scalar = C::ScalarField::rand(rng);
-        let g: C = C::generator();
-        let generators: Vec<C::Affine> = vec![C::Affine::rand(rng); max];
-        Params {
-            h: g.mul(h_scalar),
-            generators,
-        }
+        let h: C = C::Affine::rand(rng).into();
+        let generators: Vec<C::Affine> = (0..max).map(|_| C::Affine::rand(rng)).collect();
+        Params { h, generators }
+    }
+
+    /// Generate the parameters from a ChaCha20 rng seeded with the given seed, so that anyone
+    /// knowing the seed can reproduce them
+    pub fn new_params_from_seed(seed: [u8; 32], max: usize) -> Params<C> {
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        Self::new_params(&mut rng, max)
+    }
+
+    /// Generate nothing-up-my-sleeve parameters out of a public label. The seed is the SHA-256
+    /// hash of the label, and h and the generators are sampled by picking random x coordinates
+    /// until they land on the curve, so nobody knows their discrete logarithms.
+    pub fn new_params_transparent(label: &[u8], max: usize) -> Params<C> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"multifolding-poc pedersen params");
+        hasher.update(label);
+        Self::new_params_from_seed(hasher.finalize().into(), max)
     }
 
+    /// Commit to v, which must be of the same length as the parameters
+    ///
+    /// This is NOT constant time: the arkworks MSM and scalar multiplication skip zero digits of
+    /// the scalars, so the time taken depends on the values of v and r, and a prover committing
+    /// to secret witnesses may leak information about them through timing. arkworks does not
+    /// provide constant-time routines, see the `pedersen` benchmark for the timing of commitments
+    /// to low and high weight vectors.
     pub fn commit(
         params: &Params<C>,
         v: &[C::ScalarField],
         r: &C::ScalarField, // random value is provided, in order to be choosen by other parts of the protocol
-    ) -> Commitment<C> {
+    ) -> Result<Commitment<C>, CommitmentError> {
+        if v.len() != params.generators.len() {
+            return Err(CommitmentError::InvalidLength {
+                expected: params.generators.len(),
+                got: v.len(),
+            });
+        }
         let msm = C::msm(&params.generators, v).unwrap();
 
         let cm = params.h.mul(r) + msm;
-        Commitment(cm)
+        Ok(Commitment(cm))
     }
 
-    pub fn prove(
+    /// Commit to v, using the randomness r only if hiding is set. A non-hiding commitment is
+    /// \sum g_i * v_i without the h term, which anyone knowing v can recompute (e.g. for public
+    /// values), and it is the same as committing with r = 0, so it can be opened with r_w = 0.
+    pub fn commit_with_hiding(
+        params: &Params<C>,
+        v: &[C::ScalarField],
+        r: &C::ScalarField,
+        hiding: bool,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        if hiding {
+            Self::commit(params, v, r)
+        } else {
+            Self::commit(params, v, &C::ScalarField::zero())
+        }
+    }
+
+    /// Commit to v after padding it with zeros up to the length of the parameters, so that v can
+    /// be shorter than the parameters.
+    ///
+    /// The padding entries multiply their generators by zero, so the commitment is the same as
+    /// h*r + \sum_{i < |v|} g_i * v_i, and it can still be linearly combined with other
+    /// commitments to padded vectors.
+    pub fn commit_padded(
+        params: &Params<C>,
+        v: &[C::ScalarField],
+        r: &C::ScalarField,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        if v.len() > params.generators.len() {
+            return Err(CommitmentError::InvalidLength {
+                expected: params.generators.len(),
+                got: v.len(),
+            });
+        }
+        let mut padded_v = v.to_vec();
+        padded_v.resize(params.generators.len(), C::ScalarField::zero());
+        Self::commit(params, &padded_v, r)
+    }
+
+    /// Update the commitment to v after its entry v[index] changed from old_value to new_value,
+    /// adding (new_value - old_value) * g_index instead of recomputing the whole MSM. The
+    /// randomness of the commitment is unchanged.
+    pub fn commit_update(
+        commitment: &Commitment<C>,
+        params: &Params<C>,
+        index: usize,
+        old_value: &C::ScalarField,
+        new_value: &C::ScalarField,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        if index >= params.generators.len() {
+            return Err(CommitmentError::InvalidRange {
+                start: index,
+                end: index + 1,
+                len: params.generators.len(),
+            });
+        }
+        Ok(Commitment(
+            commitment.0 + params.generators[index] * (*new_value - old_value),
+        ))
+    }
+
+    /// Commit to v using only the generators in the given range of the parameters, so that v must
+    /// be of the length of the range. This is the commitment to v placed at those indices of a
+    /// vector of zeros, so the sum of commitments over disjoint ranges is the commitment to the
+    /// concatenation of their vectors, with the sum of their randomness.
+    pub fn commit_range(
+        params: &Params<C>,
+        v: &[C::ScalarField],
+        r: &C::ScalarField,
+        range: Range<usize>,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        if range.start > range.end || range.end > params.generators.len() {
+            return Err(CommitmentError::InvalidRange {
+                start: range.start,
+                end: range.end,
+                len: params.generators.len(),
+            });
+        }
+        if v.len() != range.len() {
+            return Err(CommitmentError::InvalidLength {
+                expected: range.len(),
+                got: v.len(),
+            });
+        }
+        let msm = C::msm(&params.generators[range], v).unwrap();
+
+        let cm = params.h.mul(r) + msm;
+        Ok(Commitment(cm))
+    }
+
+    /// Commit to the concatenation of the segments, each of them committed with its own randomness
+    /// over the generators at its position in the concatenation, which must be of the length of
+    /// the parameters.
+    ///
+    /// This is the sum of the [`Self::commit_range`] commitments to the segments, so it is the
+    /// commitment to the concatenation with the sum of the randomness of the segments. A segment
+    /// can then be opened on its own, by revealing its values and randomness along with the
+    /// commitment to the other segments, without revealing their values nor their randomness.
+    pub fn commit_segmented(
+        params: &Params<C>,
+        segments: &[Segment<'_, C::ScalarField>],
+    ) -> Result<Commitment<C>, CommitmentError> {
+        let len: usize = segments.iter().map(|segment| segment.values.len()).sum();
+        if len != params.generators.len() {
+            return Err(CommitmentError::InvalidLength {
+                expected: params.generators.len(),
+                got: len,
+            });
+        }
+        let mut start = 0;
+        let mut cm = Commitment(C::zero());
+        for segment in segments {
+            let end = start + segment.values.len();
+            cm = cm + Self::commit_range(params, segment.values, &segment.r, start..end)?;
+            start = end;
+        }
+        Ok(cm)
+    }
+
+    /// Commit to the entries of v yielded by the iterator, which must yield as many entries as the
+    /// length of the parameters. The MSM is accumulated over chunks of STREAMING_CHUNK_SIZE
+    /// entries, so v never needs to be materialized in memory, and the result is the same as
+    /// `commit` on the collected vector.
+    ///
+    /// The iterator is not drained past the chunk that exceeds the parameters, so when it yields
+    /// too many entries the length reported in the error is only a lower bound of its length.
+    pub fn commit_streaming(
+        params: &Params<C>,
+        v: impl Iterator<Item = C::ScalarField>,
+        r: &C::ScalarField,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        Self::commit_streaming_chunked(params, v, r, STREAMING_CHUNK_SIZE)
+    }
+
+    fn commit_streaming_chunked(
+        params: &Params<C>,
+        mut v: impl Iterator<Item = C::ScalarField>,
+        r: &C::ScalarField,
+        chunk_size: usize,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        let mut msm = C::zero();
+        let mut len = 0;
+        let mut chunk: Vec<C::ScalarField> = Vec::with_capacity(chunk_size);
+        loop {
+            chunk.clear();
+            chunk.extend(v.by_ref().take(chunk_size));
+            if chunk.is_empty() {
+                break;
+            }
+            let end = len + chunk.len();
+            if end > params.generators.len() {
+                // got is at least end, the rest of the iterator is left unconsumed
+                return Err(CommitmentError::InvalidLength {
+                    expected: params.generators.len(),
+                    got: end,
+                });
+            }
+            msm += C::msm(&params.generators[len..end], &chunk).unwrap();
+            len = end;
+        }
+        if len != params.generators.len() {
+            return Err(CommitmentError::InvalidLength {
+                expected: params.generators.len(),
+                got: len,
+            });
+        }
+
+        let cm = params.h.mul(r) + msm;
+        Ok(Commitment(cm))
+    }
+
+    /// Commit to many (v, r) pairs sharing the same parameters, computing the commitments in
+    /// parallel
+    pub fn commit_batch(
+        params: &Params<C>,
+        vs: &[(Vec<C::ScalarField>, C::ScalarField)],
+    ) -> Result<Vec<Commitment<C>>, CommitmentError> {
+        cfg_iter!(vs)
+            .map(|(v, r)| Self::commit(params, v, r))
+            .collect()
+    }
+
+    /// Check that each commitment opens to its (v, r) pair at once, instead of one MSM per
+    /// commitment. The checks C_i == h*r_i + \sum_j g_j * v_i[j] are combined with random
+    /// coefficients alpha_i sampled from the rng into
+    ///
+    ///     \sum_i alpha_i * C_i == h * (\sum_i alpha_i * r_i) + \sum_j g_j * (\sum_i alpha_i * v_i[j])
+    ///
+    /// which costs two MSMs in total, and holds for some invalid opening only with probability
+    /// 1/|F| over the coefficients. These must be unpredictable to whoever chose the openings, so
+    /// they are taken from the verifier's rng rather than from a transcript.
+    pub fn batch_verify_commitments<R: Rng>(
+        rng: &mut R,
+        params: &Params<C>,
+        openings: &[(Commitment<C>, &[C::ScalarField], C::ScalarField)],
+    ) -> bool {
+        if openings
+            .iter()
+            .any(|(_, v, _)| v.len() != params.generators.len())
+        {
+            return false;
+        }
+        let alphas: Vec<C::ScalarField> = (0..openings.len())
+            .map(|_| C::ScalarField::rand(rng))
+            .collect();
+
+        let mut v = vec![C::ScalarField::zero(); params.generators.len()];
+        let mut r = C::ScalarField::zero();
+        for ((_, v_i, r_i), alpha_i) in openings.iter().zip(alphas.iter()) {
+            for (v_j, v_ij) in v.iter_mut().zip(v_i.iter()) {
+                *v_j += *alpha_i * v_ij;
+            }
+            r += *alpha_i * r_i;
+        }
+
+        let cms: Vec<C> = openings.iter().map(|(cm, _, _)| cm.0).collect();
+        let lhs = C::msm(&C::normalize_batch(&cms), &alphas).unwrap();
+        let rhs = params.h.mul(r) + C::msm(&params.generators, &v).unwrap();
+        lhs == rhs
+    }
+
+    /// Prove knowledge of an opening (v, r) of the commitment cm. The challenge is derived from
+    /// the transcript (Fiat-Shamir), while the blinding values are sampled from the rng, since
+    /// deriving them from the transcript would let the verifier recompute them and recover v.
+    pub fn prove<R: Rng>(
+        rng: &mut R,
         params: &Params<C>,
         transcript: &mut IOPTranscript<C::ScalarField>,
         cm: &Commitment<C>,
         v: &Vec<C::ScalarField>,
         r: &C::ScalarField,
     ) -> Proof<C> {
-        let r1 = transcript.get_and_append_challenge(b"r1").unwrap();
-        let d = transcript
-            .get_and_append_challenge_vectors(b"d", v.len())
-            .unwrap();
+        let r1 = C::ScalarField::rand(rng);
+        let d: Vec<C::ScalarField> = (0..v.len()).map(|_| C::ScalarField::rand(rng)).collect();
 
         let msm = C::msm(&params.generators, &d).unwrap();
         let R: C = params.h.mul(r1) + msm;
@@ -76,18 +429,14 @@ impl<C: CurveGroup> Pedersen<C> {
 
         Proof { R, u_, ru_ }
     }
+
+    /// Verify a proof of knowledge of an opening of the commitment cm
     pub fn verify(
         params: &Params<C>,
         transcript: &mut IOPTranscript<C::ScalarField>,
         cm: Commitment<C>,
         proof: Proof<C>,
     ) -> bool {
-        // r1, d just to match Prover's transcript
-        transcript.get_and_append_challenge(b"r1").unwrap(); // r_1
-        transcript
-            .get_and_append_challenge_vectors(b"d", proof.u_.len())
-            .unwrap(); // d
-
         transcript
             .append_serializable_element(b"cm", &cm.0)
             .unwrap();
@@ -106,10 +455,37 @@ impl<C: CurveGroup> Pedersen<C> {
     }
 }
 
+impl<C: CurveGroup> CommitmentParams for Params<C> {
+    type Curve = C;
+    type Scheme = Pedersen<C>;
+}
+
+impl<C: CurveGroup> CommitmentScheme<C> for Pedersen<C> {
+    type Params = Params<C>;
+    type Commitment = Commitment<C>;
+
+    fn commit(
+        params: &Params<C>,
+        v: &[C::ScalarField],
+        r: &C::ScalarField,
+    ) -> Result<Commitment<C>, CommitmentError> {
+        Pedersen::<C>::commit(params, v, r)
+    }
+
+    fn add(a: &Commitment<C>, b: &Commitment<C>) -> Commitment<C> {
+        a + b
+    }
+
+    fn scalar_mul(a: &Commitment<C>, s: &C::ScalarField) -> Commitment<C> {
+        a * s
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ark_bls12_381::{Fr, G1Projective};
+    use ark_bls12_381::{Fr, G2Projective};
+    use ark_ec::AffineRepr;
 
     #[test]
     fn test_pedersen_commitment() {
@@ -129,9 +505,546 @@ mod tests {
         let v: Vec<Fr> = vec![Fr::rand(&mut rng); n];
         let r: Fr = Fr::rand(&mut rng);
 
-        let cm = Pedersen::<G1Projective>::commit(&params, &v, &r);
-        let proof = Pedersen::<G1Projective>::prove(&params, &mut transcript_p, &cm, &v, &r);
+        let cm = Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap();
+        let proof =
+            Pedersen::<G1Projective>::prove(&mut rng, &params, &mut transcript_p, &cm, &v, &r);
         let v = Pedersen::<G1Projective>::verify(&params, &mut transcript_v, cm, proof);
         assert!(v);
     }
+
+    #[test]
+    fn test_commitment_ops() {
+        let mut rng = ark_std::test_rng();
+
+        let a = Commitment(G1Projective::rand(&mut rng));
+        let b = Commitment(G1Projective::rand(&mut rng));
+        let rho = Fr::rand(&mut rng);
+
+        assert_eq!((&a + &b).0, a.0 + b.0);
+        assert_eq!(a.clone() + b.clone(), &a + &b);
+        assert_eq!((&a * &rho).0, a.0 * rho);
+        assert_eq!(a.clone() * rho, &a * &rho);
+        assert_eq!((a.clone() + b.clone() * rho).0, a.0 + b.0 * rho);
+        assert_eq!(Pedersen::<G1Projective>::add(&a, &b), a.clone() + b.clone());
+        assert_eq!(Pedersen::<G1Projective>::scalar_mul(&a, &rho), a * rho);
+    }
+
+    #[test]
+    fn test_commitment_bytes() {
+        let mut rng = ark_std::test_rng();
+
+        let cm = Commitment(G1Projective::rand(&mut rng));
+        let compressed = cm.to_bytes_compressed();
+        let uncompressed = cm.to_bytes_uncompressed();
+        assert_eq!(
+            Commitment::<G1Projective>::from_bytes_compressed(&compressed).unwrap(),
+            cm
+        );
+        assert_eq!(
+            Commitment::<G1Projective>::from_bytes_uncompressed(&uncompressed).unwrap(),
+            cm
+        );
+        // a G1 point is 48 bytes compressed and 96 bytes uncompressed
+        assert_eq!((compressed.len(), uncompressed.len()), (48, 96));
+        assert_eq!(2 * compressed.len(), uncompressed.len());
+
+        // the encodings are not interchangeable, and truncated bytes are rejected
+        assert!(Commitment::<G1Projective>::from_bytes_compressed(&uncompressed).is_err());
+        assert!(Commitment::<G1Projective>::from_bytes_uncompressed(&compressed).is_err());
+        assert!(matches!(
+            Commitment::<G1Projective>::from_bytes_compressed(&compressed[1..]),
+            Err(CommitmentError::Deserialization(_))
+        ));
+
+        // and so are bytes left after the point
+        let mut padded = compressed.clone();
+        padded.push(0);
+        assert!(matches!(
+            Commitment::<G1Projective>::from_bytes_compressed(&padded),
+            Err(CommitmentError::TrailingBytes { got: 1 })
+        ));
+        let mut padded = uncompressed.clone();
+        padded.extend_from_slice(&[0; 3]);
+        assert!(matches!(
+            Commitment::<G1Projective>::from_bytes_uncompressed(&padded),
+            Err(CommitmentError::TrailingBytes { got: 3 })
+        ));
+    }
+
+    /// The commitments only depend on the group, so they work the same over G2, which has the
+    /// same scalar field as G1
+    #[test]
+    fn test_pedersen_other_group() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G2Projective>::new_params(&mut rng, n);
+        let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Fr = Fr::rand(&mut rng);
+        let cm = Pedersen::<G2Projective>::commit(&params, &v, &r).unwrap();
+
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"pedersen_test");
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"pedersen_test");
+        let proof =
+            Pedersen::<G2Projective>::prove(&mut rng, &params, &mut transcript_p, &cm, &v, &r);
+        assert!(Pedersen::<G2Projective>::verify(
+            &params,
+            &mut transcript_v,
+            cm.clone(),
+            proof
+        ));
+
+        // and they are homomorphic over any group
+        let rho = Fr::rand(&mut rng);
+        assert_eq!(
+            Pedersen::<G2Projective>::scalar_mul(&cm, &rho),
+            Pedersen::<G2Projective>::commit(&params, &vec_scalar_mul(&v, &rho), &(r * rho))
+                .unwrap()
+        );
+
+        // the BLS12-381 aliases are the G1 types
+        let params: Bls12Params = Bls12Pedersen::new_params(&mut rng, n);
+        let cm: Bls12Commitment = Bls12Pedersen::commit(&params, &v, &r).unwrap();
+        assert_eq!(
+            cm,
+            Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pedersen_params_from_seed() {
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params_from_seed([1u8; 32], n);
+        assert_eq!(
+            params,
+            Pedersen::<G1Projective>::new_params_from_seed([1u8; 32], n)
+        );
+        assert_ne!(
+            params,
+            Pedersen::<G1Projective>::new_params_from_seed([2u8; 32], n)
+        );
+        // the generators are independent of each other
+        for i in 1..n {
+            assert!(!params.generators[..i].contains(&params.generators[i]));
+        }
+
+        let params = Pedersen::<G1Projective>::new_params_transparent(b"test", n);
+        assert_eq!(
+            params,
+            Pedersen::<G1Projective>::new_params_transparent(b"test", n)
+        );
+        assert_ne!(
+            params,
+            Pedersen::<G1Projective>::new_params_transparent(b"other test", n)
+        );
+    }
+
+    /// h is not the generator times a scalar drawn from the rng the parameters come from, which
+    /// would let anyone knowing the seed open commitments to any value
+    #[test]
+    fn test_pedersen_params_no_known_scalar() {
+        const n: usize = 10;
+        let seed = [1u8; 32];
+        let params = Pedersen::<G1Projective>::new_params_from_seed(seed, n);
+
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        let g = ark_bls12_381::G1Affine::generator();
+        for _ in 0..4 * n {
+            let point: G1Projective = g * Fr::rand(&mut rng);
+            assert_ne!(params.h, point);
+            assert!(!params.generators.contains(&point.into_affine()));
+        }
+    }
+
+    #[test]
+    fn test_pedersen_commit_msm() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Fr = Fr::rand(&mut rng);
+        let cm = Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap();
+
+        // naive computation of h*r + \sum g_i * v_i
+        let mut naive = params.h * r;
+        for (g_i, v_i) in params.generators.iter().zip(v.iter()) {
+            naive += g_i.into_group() * v_i;
+        }
+        assert_eq!(cm.0, naive);
+    }
+
+    #[test]
+    fn test_pedersen_commit_length() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+        let r: Fr = Fr::rand(&mut rng);
+
+        // exact length
+        let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let cm = Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap();
+        assert_eq!(
+            Pedersen::<G1Projective>::commit_padded(&params, &v, &r).unwrap(),
+            cm
+        );
+
+        // shorter vectors are only accepted when padded, and padding does not change the
+        // commitment of the vector extended with zeros
+        let short_v = v[..n - 3].to_vec();
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit(&params, &short_v, &r),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == n - 3
+        ));
+        let mut extended_v = short_v.clone();
+        extended_v.resize(n, Fr::zero());
+        assert_eq!(
+            Pedersen::<G1Projective>::commit_padded(&params, &short_v, &r).unwrap(),
+            Pedersen::<G1Projective>::commit(&params, &extended_v, &r).unwrap()
+        );
+
+        // longer vectors are always rejected
+        let long_v = [v, vec![Fr::rand(&mut rng)]].concat();
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit(&params, &long_v, &r),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == n + 1
+        ));
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_padded(&params, &long_v, &r),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == n + 1
+        ));
+    }
+
+    #[test]
+    fn test_pedersen_commit_non_hiding() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Fr = Fr::rand(&mut rng);
+        let cm = Pedersen::<G1Projective>::commit_with_hiding(&params, &v, &r, false).unwrap();
+
+        // no h term, just \sum g_i * v_i
+        let mut msm = G1Projective::zero();
+        for (g_i, v_i) in params.generators.iter().zip(v.iter()) {
+            msm += g_i.into_group() * v_i;
+        }
+        assert_eq!(cm.0, msm);
+        assert_eq!(
+            cm,
+            Pedersen::<G1Projective>::commit(&params, &v, &Fr::zero()).unwrap()
+        );
+
+        // the hiding commitment does use r
+        assert_eq!(
+            Pedersen::<G1Projective>::commit_with_hiding(&params, &v, &r, true).unwrap(),
+            Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap()
+        );
+        assert_ne!(
+            Pedersen::<G1Projective>::commit_with_hiding(&params, &v, &r, true).unwrap(),
+            cm
+        );
+    }
+
+    #[test]
+    fn test_pedersen_commit_update() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+        let mut w: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Fr = Fr::rand(&mut rng);
+        let mut cm = Pedersen::<G1Projective>::commit(&params, &w, &r).unwrap();
+
+        for index in [0, 3, 3, n - 1] {
+            let new_value = Fr::rand(&mut rng);
+            cm =
+                Pedersen::<G1Projective>::commit_update(&cm, &params, index, &w[index], &new_value)
+                    .unwrap();
+            w[index] = new_value;
+            assert_eq!(
+                cm,
+                Pedersen::<G1Projective>::commit(&params, &w, &r).unwrap()
+            );
+        }
+
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_update(&cm, &params, n, &w[0], &w[1]),
+            Err(CommitmentError::InvalidRange {
+                start: n,
+                end: 11,
+                len: n
+            })
+        ));
+    }
+
+    #[test]
+    fn test_pedersen_commit_range() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        // split w into its first k entries and the rest, and commit to each part separately
+        const k: usize = 4;
+        let w: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r_1: Fr = Fr::rand(&mut rng);
+        let r_2: Fr = Fr::rand(&mut rng);
+        let cm_1 = Pedersen::<G1Projective>::commit_range(&params, &w[..k], &r_1, 0..k).unwrap();
+        let cm_2 = Pedersen::<G1Projective>::commit_range(&params, &w[k..], &r_2, k..n).unwrap();
+
+        let cm = Pedersen::<G1Projective>::commit(&params, &w, &(r_1 + r_2)).unwrap();
+        assert_eq!(Pedersen::<G1Projective>::add(&cm_1, &cm_2), cm);
+
+        // a range commitment is the commitment to the vector padded with zeros around the range
+        let mut padded_w = vec![Fr::zero(); n];
+        padded_w[k..].copy_from_slice(&w[k..]);
+        assert_eq!(
+            cm_2,
+            Pedersen::<G1Projective>::commit(&params, &padded_w, &r_2).unwrap()
+        );
+
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_range(&params, &w[k..], &r_2, k..n + 1),
+            Err(CommitmentError::InvalidRange {
+                start: k,
+                end: 11,
+                len: n
+            })
+        ));
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_range(&params, &w[..k], &r_1, 0..k + 1),
+            Err(CommitmentError::InvalidLength {
+                expected: 5,
+                got: k
+            })
+        ));
+    }
+
+    #[test]
+    fn test_pedersen_commit_segmented() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        // a witness whose first k entries are revealed later
+        const k: usize = 4;
+        let w: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let public = Segment {
+            values: &w[..k],
+            r: Fr::rand(&mut rng),
+        };
+        let private = Segment {
+            values: &w[k..],
+            r: Fr::rand(&mut rng),
+        };
+        let cm = Pedersen::<G1Projective>::commit_segmented(&params, &[public, private]).unwrap();
+        assert_eq!(
+            cm,
+            Pedersen::<G1Projective>::commit(&params, &w, &(public.r + private.r)).unwrap()
+        );
+
+        // open the public segment, sending the commitment to the private one along with it
+        let cm_private =
+            Pedersen::<G1Projective>::commit_range(&params, private.values, &private.r, k..n)
+                .unwrap();
+        let cm_public =
+            Pedersen::<G1Projective>::commit_range(&params, public.values, &public.r, 0..k)
+                .unwrap();
+        assert_eq!(&cm_public + &cm_private, cm);
+        // a tampered public segment does not open the commitment
+        let mut bad_values = w[..k].to_vec();
+        bad_values[0] += Fr::from(1u64);
+        let bad_cm_public =
+            Pedersen::<G1Projective>::commit_range(&params, &bad_values, &public.r, 0..k).unwrap();
+        assert_ne!(&bad_cm_public + &cm_private, cm);
+
+        // the segments must cover the parameters
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_segmented(&params, &[public]),
+            Err(CommitmentError::InvalidLength {
+                expected: n,
+                got: k
+            })
+        ));
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_segmented(&params, &[public, private, public]),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == n + k
+        ));
+    }
+
+    #[test]
+    fn test_pedersen_commit_streaming() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Fr = Fr::rand(&mut rng);
+        let cm = Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap();
+
+        assert_eq!(
+            Pedersen::<G1Projective>::commit_streaming(&params, v.iter().copied(), &r).unwrap(),
+            cm
+        );
+        // chunks that do and do not divide the length
+        for chunk_size in [1, 3, 5, n, n + 1] {
+            assert_eq!(
+                Pedersen::<G1Projective>::commit_streaming_chunked(
+                    &params,
+                    v.iter().copied(),
+                    &r,
+                    chunk_size
+                )
+                .unwrap(),
+                cm
+            );
+        }
+
+        // the iterator must yield exactly as many entries as the parameters
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_streaming_chunked(
+                &params,
+                v.iter().copied().take(n - 1),
+                &r,
+                3
+            ),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == n - 1
+        ));
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_streaming_chunked(
+                &params,
+                v.iter().copied().chain(v.iter().copied()),
+                &r,
+                3
+            ),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == 12
+        ));
+        // the iterator is not drained past the first chunk out of the parameters
+        assert!(matches!(
+            Pedersen::<G1Projective>::commit_streaming_chunked(
+                &params,
+                core::iter::repeat(r),
+                &r,
+                3
+            ),
+            Err(CommitmentError::InvalidLength { expected: n, got }) if got == 12
+        ));
+    }
+
+    #[test]
+    fn test_pedersen_commit_batch() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        let vs: Vec<(Vec<Fr>, Fr)> = (0..5)
+            .map(|_| {
+                let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+                (v, Fr::rand(&mut rng))
+            })
+            .collect();
+
+        let cms = Pedersen::<G1Projective>::commit_batch(&params, &vs).unwrap();
+        assert_eq!(cms.len(), vs.len());
+        for ((v, r), cm) in vs.iter().zip(cms) {
+            assert_eq!(cm, Pedersen::<G1Projective>::commit(&params, v, r).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_pedersen_batch_verify_commitments() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        let vs: Vec<(Vec<Fr>, Fr)> = (0..5)
+            .map(|_| {
+                let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+                (v, Fr::rand(&mut rng))
+            })
+            .collect();
+        let cms = Pedersen::<G1Projective>::commit_batch(&params, &vs).unwrap();
+        let openings: Vec<(Commitment<G1Projective>, &[Fr], Fr)> = cms
+            .iter()
+            .zip(vs.iter())
+            .map(|(cm, (v, r))| (cm.clone(), v.as_slice(), *r))
+            .collect();
+
+        assert!(Pedersen::<G1Projective>::batch_verify_commitments(
+            &mut rng, &params, &openings
+        ));
+        assert!(Pedersen::<G1Projective>::batch_verify_commitments(
+            &mut rng,
+            &params,
+            &[]
+        ));
+
+        // tampering with the commitment, v or r of any single entry makes the batch fail
+        let mut bad_v = vs[2].0.clone();
+        bad_v[0] += Fr::from(1u64);
+        let short_v = &vs[2].0[1..];
+        for tampered in [
+            (cms[2].clone() + cms[0].clone(), vs[2].0.as_slice(), vs[2].1),
+            (cms[2].clone(), bad_v.as_slice(), vs[2].1),
+            (cms[2].clone(), vs[2].0.as_slice(), vs[2].1 + Fr::from(1u64)),
+            (cms[2].clone(), short_v, vs[2].1),
+        ] {
+            let mut bad_openings = openings.clone();
+            bad_openings[2] = tampered;
+            assert!(!Pedersen::<G1Projective>::batch_verify_commitments(
+                &mut rng,
+                &params,
+                &bad_openings
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pedersen_bad_opening() {
+        let mut rng = ark_std::test_rng();
+
+        const n: usize = 10;
+        let params = Pedersen::<G1Projective>::new_params(&mut rng, n);
+
+        let v: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let r: Fr = Fr::rand(&mut rng);
+        let cm = Pedersen::<G1Projective>::commit(&params, &v, &r).unwrap();
+
+        // tampered v
+        let mut bad_v = v.clone();
+        bad_v[0] += Fr::from(1u64);
+        // tampered r
+        let bad_r = r + Fr::from(1u64);
+
+        for (v_i, r_i) in [(&bad_v, &r), (&v, &bad_r)] {
+            let mut transcript_p = IOPTranscript::<Fr>::new(b"pedersen_test");
+            transcript_p.append_message(b"init", b"init").unwrap();
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"pedersen_test");
+            transcript_v.append_message(b"init", b"init").unwrap();
+
+            let proof = Pedersen::<G1Projective>::prove(
+                &mut rng,
+                &params,
+                &mut transcript_p,
+                &cm,
+                v_i,
+                r_i,
+            );
+            assert!(!Pedersen::<G1Projective>::verify(
+                &params,
+                &mut transcript_v,
+                cm.clone(),
+                proof
+            ));
+        }
+    }
 }
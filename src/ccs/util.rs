@@ -1,84 +1,164 @@
-use ark_ff::PrimeField;
+use ark_ff::{Field, PrimeField};
 use ark_poly::DenseMultilinearExtension;
-use ark_poly::MultilinearExtension;
-use std::ops::Add;
+use ark_serialize::CanonicalDeserialize;
+use ark_std::cfg_iter;
 
-use crate::espresso::multilinear_polynomial::fix_variables;
-use crate::espresso::multilinear_polynomial::scalar_mul;
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::util::hypercube::BooleanHypercube;
-use crate::util::mle::matrix_to_mle;
-use crate::util::mle::vec_to_mle;
-use crate::util::vec::Matrix;
+use crate::ccs::ccs::CCSError;
+use crate::util::mle::{eval_mle_at, vec_to_mle};
+use crate::util::vec::{mat_vec_mul_sparse, SparseMatrix};
 
 /// Return a vector of evaluations p_j(r) = \sum_{y \in {0,1}^s'} M_j(r, y) * z(y)
 /// for all j values in 0..self.t
-pub fn compute_all_sum_Mz_evals<F: PrimeField>(
-    vec_M: &[Matrix<F>],
+///
+/// Each M_j z product is computed exactly once, however many multisets reference M_j, and the
+/// evaluations are indexed by the matrices, not by the multisets. Identical matrices stored at
+/// different indices are still computed once each, so use [`crate::ccs::ccs::CCS::simplify`] to
+/// merge them first.
+///
+/// The point r may be over an extension field E of the field F of the matrices and z vector, e.g.
+/// to sample it from a larger field for soundness, in which case the evaluations are over E. The
+/// M_j * z products are still computed over F.
+///
+/// Return `CCSError::InvalidWitnessLength` if z is not of the length n of the columns of the
+/// matrices, and `CCSError::InvalidChallengeLength` if r is not of length s.
+pub fn compute_all_sum_Mz_evals<F: PrimeField, E: Field<BasePrimeField = F>>(
+    vec_M: &[SparseMatrix<F>],
     z: &Vec<F>,
-    r: &[F],
-    s_prime: usize,
-) -> Vec<F> {
-    // Convert z to MLE
-    let z_y_mle = vec_to_mle(s_prime, z);
-    // Convert all matrices to MLE
-    let M_x_y_mle: Vec<DenseMultilinearExtension<F>> =
-        vec_M.iter().cloned().map(matrix_to_mle).collect();
-
-    let mut v = Vec::with_capacity(M_x_y_mle.len());
-    for M_i in M_x_y_mle {
-        let sum_Mz = compute_sum_Mz(M_i, &z_y_mle, s_prime);
-        let v_i = sum_Mz.evaluate(r).unwrap();
-        v.push(v_i);
+    r: &[E],
+    s: usize,
+) -> Result<Vec<E>, CCSError> {
+    if let Some(M_j) = vec_M.iter().find(|M_j| M_j.n_cols != z.len()) {
+        return Err(CCSError::InvalidWitnessLength {
+            expected: M_j.n_cols,
+            got: z.len(),
+        });
+    }
+    if r.len() != s {
+        return Err(CCSError::InvalidChallengeLength {
+            expected: s,
+            got: r.len(),
+        });
+    }
+    PreparedWitness::new(vec_M, z, s).evaluate(r)
+}
+
+/// Deserialize `len` compressed elements out of the bytes, advancing them past the elements. The
+/// elements are validated (e.g. group elements must be on the curve and in the prime order
+/// subgroup), so malformed bytes give an error instead of an invalid element.
+pub(crate) fn read_compressed<T: CanonicalDeserialize>(
+    bytes: &mut &[u8],
+    len: usize,
+) -> Result<Vec<T>, CCSError> {
+    (0..len)
+        .map(|_| Ok(T::deserialize_compressed(&mut *bytes)?))
+        .collect()
+}
+
+/// The M_j * z vectors of a z vector, for all j values in 0..self.t
+///
+/// The M_j * z vector is the evaluation table of \sum_{y \in {0,1}^s'} M_j(x, y) * z(y), so once
+/// it is computed, evaluating that polynomial at a point is a cheap fold of the table, which does
+/// not touch the sparse matrices anymore. This is useful to evaluate the same z vector at many
+/// points.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PreparedWitness<F: PrimeField> {
+    s: usize,
+    vec_Mz: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> PreparedWitness<F> {
+    /// Compute the M_j * z vectors
+    pub fn new(vec_M: &[SparseMatrix<F>], z: &[F], s: usize) -> Self {
+        let vec_Mz = cfg_iter!(vec_M)
+            .map(|M_j| mat_vec_mul_sparse(M_j, z))
+            .collect();
+        PreparedWitness { s, vec_Mz }
+    }
+
+    /// Wrap the M_j * z vectors already computed by [`crate::ccs::ccs::CCS::compute_Mz_vectors`]
+    /// for a CCS with m <= 2^s constraints
+    pub fn from_Mz_vectors(vec_Mz: Vec<Vec<F>>, s: usize) -> Self {
+        PreparedWitness { s, vec_Mz }
+    }
+
+    /// Return the evaluations p_j(r) = \sum_{y \in {0,1}^s'} M_j(r, y) * z(y) for all j values, at
+    /// a point r over F or over an extension field of F. Return
+    /// `CCSError::InvalidChallengeLength` if r is not of length s.
+    pub fn evaluate<E: Field<BasePrimeField = F>>(&self, r: &[E]) -> Result<Vec<E>, CCSError> {
+        if r.len() != self.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: self.s,
+                got: r.len(),
+            });
+        }
+        // the evaluations are independent of each other, and collect() keeps them in the order of
+        // the matrices
+        Ok(cfg_iter!(self.vec_Mz)
+            .map(|Mz_j| eval_mle_at(Mz_j, r))
+            .collect())
     }
-    v
 }
 
 /// Return the multilinear polynomial p(x) = \sum_{y \in {0,1}^s'} M_j(x, y) * z(y)
+///
+/// Its evaluations over the boolean hypercube are the entries of the M_j * z vector, so instead of
+/// summing M_j(x, y) * z(y) over all the y points, compute the sparse matrix-vector product and
+/// interpolate it as an s-variable MLE.
 pub fn compute_sum_Mz<F: PrimeField>(
-    M_j: DenseMultilinearExtension<F>,
-    z: &DenseMultilinearExtension<F>,
-    s_prime: usize,
+    M_j: &SparseMatrix<F>,
+    z: &[F],
+    s: usize,
 ) -> DenseMultilinearExtension<F> {
-    let mut sum_Mz = DenseMultilinearExtension {
-        evaluations: vec![F::zero(); M_j.evaluations.len()],
-        num_vars: M_j.num_vars - s_prime,
-    };
-
-    let bhc = BooleanHypercube::new(s_prime);
-    for y in bhc.into_iter() {
-        // In a slightly counter-intuitive fashion fix_variables() fixes the right-most variables of the polynomial. So
-        // for a polynomial M(x,y) and a random field element r, if we do fix_variables(M,r) we will get M(x,r).
-        let M_j_y = fix_variables(&M_j, &y);
-        let z_y = z.evaluate(&y).unwrap();
-        let M_j_z = scalar_mul(&M_j_y, &z_y);
-        sum_Mz = sum_Mz.add(M_j_z);
-    }
-    sum_Mz
+    let Mz = mat_vec_mul_sparse(M_j, z);
+    vec_to_mle(s, &Mz)
 }
 
 #[cfg(test)]
 pub mod test {
     use super::*;
 
-    use ark_bls12_381::{Fr, G1Projective};
+    use ark_bls12_381::Fr;
+    use ark_poly::MultilinearExtension;
     use ark_std::test_rng;
     use ark_std::One;
     use ark_std::UniformRand;
     use ark_std::Zero;
 
+    use core::ops::Add;
+
     use crate::ccs::ccs::test::{get_test_ccs, get_test_z};
+    use crate::ccs::ccs::CCS;
+    use crate::espresso::multilinear_polynomial::fix_variables;
+    use crate::espresso::multilinear_polynomial::scalar_mul;
     use crate::espresso::multilinear_polynomial::testing_code::fix_last_variables;
     use crate::espresso::virtual_polynomial::eq_eval;
+    use crate::util::hypercube::BooleanHypercube;
+    use crate::util::mle::matrix_to_mle;
 
     use crate::ccs::util::compute_sum_Mz;
 
+    use ark_ff::{Fp2, Fp2Config, MontFp};
+
+    /// The quadratic extension Fr[u] / (u^2 - 7) of the BLS12-381 scalar field
+    struct FrQuadConfig;
+
+    impl Fp2Config for FrQuadConfig {
+        type Fp = Fr;
+        // 7 generates the multiplicative group of Fr, so it is not a square
+        const NONRESIDUE: Fr = MontFp!("7");
+        const FROBENIUS_COEFF_FP2_C1: &'static [Fr] = &[MontFp!("1"), MontFp!("-1")];
+    }
+
+    type FrQuad = Fp2<FrQuadConfig>;
+
     #[test]
     fn test_compute_sum_Mz_over_boolean_hypercube() -> () {
-        let ccs = get_test_ccs::<G1Projective>();
+        let ccs = get_test_ccs::<Fr>();
         let z = get_test_z(3);
         ccs.check_relation(&z).unwrap();
-        let z_mle = vec_to_mle(ccs.s_prime, &z);
 
         // check that evaluating over all the values x over the boolean hypercube, the result of
         // the next for loop is equal to 0
@@ -88,8 +168,7 @@ pub mod test {
             for i in 0..ccs.q {
                 let mut Sj_prod = Fr::one();
                 for j in ccs.S[i].clone() {
-                    let M_j = matrix_to_mle(ccs.M[j].clone());
-                    let sum_Mz = compute_sum_Mz(M_j, &z_mle, ccs.s_prime);
+                    let sum_Mz = compute_sum_Mz(&ccs.M[j], &z, ccs.s);
                     let sum_Mz_x = sum_Mz.evaluate(&x).unwrap();
                     Sj_prod *= sum_Mz_x;
                 }
@@ -99,6 +178,203 @@ pub mod test {
         }
     }
 
+    /// Check that computing \sum_{y \in {0,1}^s'} M_j(x, y) * z(y) through the sparse M_j * z product
+    /// gives the same polynomial as summing the dense M_j(x, y) MLE over all the y points
+    #[test]
+    fn test_compute_sum_Mz_against_dense() -> () {
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let z_mle = vec_to_mle(ccs.s_prime, &z);
+
+        for M_j in ccs.M.iter() {
+            let M_j_mle = matrix_to_mle(M_j.to_dense());
+
+            let mut expected = DenseMultilinearExtension::<Fr>::zero();
+            for y in BooleanHypercube::new(ccs.s_prime) {
+                // fix_variables() fixes the first variables, which correspond to the columns (y)
+                let M_j_y = fix_variables(&M_j_mle, &y);
+                let z_y = z_mle.evaluate(&y).unwrap();
+                expected = expected.add(scalar_mul(&M_j_y, &z_y));
+            }
+
+            assert_eq!(compute_sum_Mz(M_j, &z, ccs.s), expected);
+        }
+    }
+
+    /// Evaluating a prepared witness at many points gives the same evaluations as computing them
+    /// from scratch
+    #[test]
+    fn test_prepared_witness() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let prepared = PreparedWitness::new(&ccs.M, &z, ccs.s);
+
+        for _ in 0..5 {
+            let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+            let expected: Vec<Fr> = ccs
+                .M
+                .iter()
+                .map(|M_j| compute_sum_Mz(M_j, &z, ccs.s).evaluate(&r).unwrap())
+                .collect();
+            assert_eq!(prepared.evaluate(&r).unwrap(), expected);
+            assert_eq!(
+                compute_all_sum_Mz_evals(&ccs.M, &z, &r, ccs.s).unwrap(),
+                expected
+            );
+        }
+        let r: Vec<Fr> = (0..ccs.s + 1).map(|_| Fr::rand(&mut rng)).collect();
+        assert!(matches!(
+            prepared.evaluate(&r),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 3
+            })
+        ));
+    }
+
+    /// Multisets sharing a matrix don't compute its M_j z product twice, and once the identical
+    /// matrices are merged there is one product per distinct matrix
+    #[test]
+    fn test_compute_all_sum_Mz_evals_shared_matrices() -> () {
+        let mut rng = test_rng();
+
+        // A z ∘ B z - C z = 0 and A z ∘ C z - A z ∘ C z = 0, where A and C appear in both multisets
+        let ccs = get_test_ccs::<Fr>();
+        let shared_ccs = CCS::new(
+            ccs.M.clone(),
+            vec![vec![0, 1], vec![2], vec![0, 2], vec![0, 2]],
+            vec![Fr::one(), -Fr::one(), Fr::one(), -Fr::one()],
+            ccs.l,
+        )
+        .unwrap();
+        let z = get_test_z(3);
+        shared_ccs.check_relation(&z).unwrap();
+        let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+        let prepared = PreparedWitness::new(&shared_ccs.M, &z, shared_ccs.s);
+        assert_eq!(prepared.vec_Mz.len(), 3);
+        let v = compute_all_sum_Mz_evals(&shared_ccs.M, &z, &r, shared_ccs.s).unwrap();
+        assert_eq!(v, compute_all_sum_Mz_evals(&ccs.M, &z, &r, ccs.s).unwrap());
+
+        // a copy of A at another index is a product more, until the CCS is simplified
+        let mut M = ccs.M.clone();
+        M.push(ccs.M[0].clone());
+        let duplicated_ccs = CCS::new(
+            M,
+            vec![vec![0, 1], vec![2], vec![3, 2], vec![0, 2]],
+            vec![Fr::one(), -Fr::one(), Fr::one(), -Fr::one()],
+            ccs.l,
+        )
+        .unwrap();
+        assert_eq!(
+            PreparedWitness::new(&duplicated_ccs.M, &z, ccs.s)
+                .vec_Mz
+                .len(),
+            4
+        );
+        let simplified = duplicated_ccs.simplify();
+        assert_eq!(simplified.S, shared_ccs.S);
+        assert_eq!(
+            PreparedWitness::new(&simplified.M, &z, ccs.s).vec_Mz.len(),
+            3
+        );
+        assert_eq!(
+            compute_all_sum_Mz_evals(&simplified.M, &z, &r, simplified.s).unwrap(),
+            v
+        );
+    }
+
+    #[test]
+    fn test_compute_all_sum_Mz_evals_invalid_lengths() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+        let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+        let short_z = z[..ccs.n - 1].to_vec();
+        assert!(matches!(
+            compute_all_sum_Mz_evals(&ccs.M, &short_z, &r, ccs.s),
+            Err(CCSError::InvalidWitnessLength {
+                expected: 6,
+                got: 5
+            })
+        ));
+        let long_r = [r.clone(), vec![Fr::rand(&mut rng)]].concat();
+        assert!(matches!(
+            compute_all_sum_Mz_evals(&ccs.M, &z, &long_r, ccs.s),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 3
+            })
+        ));
+        assert!(matches!(
+            compute_all_sum_Mz_evals(&ccs.M, &z, &r[..1], ccs.s),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 1
+            })
+        ));
+    }
+
+    /// The evaluations at a point of the quadratic extension are the ones of the MLEs of the
+    /// M_j * z vectors seen over the extension, and the base field points give the evaluations
+    /// over the base field
+    #[test]
+    fn test_compute_all_sum_Mz_evals_extension() -> () {
+        let mut rng = test_rng();
+        let ccs = get_test_ccs::<Fr>();
+        let z = get_test_z(3);
+
+        let r: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let lifted_r: Vec<FrQuad> = r
+            .iter()
+            .map(|r_i| FrQuad::from_base_prime_field(*r_i))
+            .collect();
+        let evals = compute_all_sum_Mz_evals(&ccs.M, &z, &r, ccs.s).unwrap();
+        let lifted_evals: Vec<FrQuad> = evals
+            .iter()
+            .map(|e| FrQuad::from_base_prime_field(*e))
+            .collect();
+        assert_eq!(
+            compute_all_sum_Mz_evals(&ccs.M, &z, &lifted_r, ccs.s).unwrap(),
+            lifted_evals
+        );
+
+        // \sum_x eq(x, r) * (M_j z)[x] over the extension
+        let r: Vec<FrQuad> = (0..ccs.s).map(|_| FrQuad::rand(&mut rng)).collect();
+        let expected: Vec<FrQuad> = ccs
+            .M
+            .iter()
+            .map(|M_j| {
+                let Mz = mat_vec_mul_sparse(M_j, &z);
+                (0..Mz.len())
+                    .map(|x| {
+                        let eq_x_r: FrQuad = r
+                            .iter()
+                            .enumerate()
+                            .map(|(i, r_i)| {
+                                if (x >> i) & 1 == 1 {
+                                    *r_i
+                                } else {
+                                    FrQuad::one() - r_i
+                                }
+                            })
+                            .product();
+                        eq_x_r * FrQuad::from_base_prime_field(Mz[x])
+                    })
+                    .sum()
+            })
+            .collect();
+        let evals = compute_all_sum_Mz_evals(&ccs.M, &z, &r, ccs.s).unwrap();
+        assert_eq!(evals, expected);
+        assert_eq!(ccs.compute_v_j(&z, &r).unwrap(), evals);
+        // the evaluations are not in the base field
+        assert!(evals.iter().any(|v_j| !v_j.c1.is_zero()));
+    }
+
     /// Given M(x,y) matrix and a random field element `r`, test that ~M(r,y) is is an s'-variable polynomial which
     /// compresses every column j of the M(x,y) matrix by performing a random linear combination between the elements
     /// of the column and the values eq_i(r) where i is the row of that element
@@ -124,9 +400,9 @@ pub mod test {
         let mut rng = test_rng();
 
         // s = 2, s' = 3
-        let ccs = get_test_ccs::<G1Projective>();
+        let ccs = get_test_ccs::<Fr>();
 
-        let M = ccs.M[0].clone();
+        let M = ccs.M[0].to_dense();
         let M_mle = matrix_to_mle(M.clone());
 
         // Fix the polynomial ~M(r,y)
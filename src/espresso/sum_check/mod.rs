@@ -206,3 +206,47 @@ impl<F: PrimeField> SumCheck<F> for PolyIOP<F> {
         res
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::espresso::multilinear_polynomial::testing_code::random_mle_list;
+    use ark_bls12_381::Fr;
+    use ark_std::{test_rng, One};
+
+    /// Run the sumcheck on a random product of MLEs, and check that the subclaim returned by the
+    /// verifier is the evaluation of the polynomial at the sumcheck challenge point
+    #[test]
+    fn test_sumcheck() -> Result<(), PolyIOPErrors> {
+        let mut rng = test_rng();
+
+        let nv = 5;
+        let (mles, sum) = random_mle_list::<Fr, _>(nv, 3, &mut rng);
+        let mut poly = VirtualPolynomial::new(nv);
+        poly.add_mle_list(mles, Fr::one()).unwrap();
+
+        let mut transcript_p = <PolyIOP<Fr> as SumCheck<Fr>>::init_transcript();
+        let proof = <PolyIOP<Fr> as SumCheck<Fr>>::prove(&poly, &mut transcript_p)?;
+        assert_eq!(<PolyIOP<Fr> as SumCheck<Fr>>::extract_sum(&proof), sum);
+
+        let mut transcript_v = <PolyIOP<Fr> as SumCheck<Fr>>::init_transcript();
+        let subclaim =
+            <PolyIOP<Fr> as SumCheck<Fr>>::verify(sum, &proof, &poly.aux_info, &mut transcript_v)?;
+        assert_eq!(subclaim.point, proof.point);
+        assert_eq!(
+            poly.evaluate(&subclaim.point).unwrap(),
+            subclaim.expected_evaluation
+        );
+
+        // a wrong claimed sum is rejected
+        let mut transcript_v = <PolyIOP<Fr> as SumCheck<Fr>>::init_transcript();
+        assert!(<PolyIOP<Fr> as SumCheck<Fr>>::verify(
+            sum + Fr::one(),
+            &proof,
+            &poly.aux_info,
+            &mut transcript_v
+        )
+        .is_err());
+        Ok(())
+    }
+}
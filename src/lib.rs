@@ -2,8 +2,17 @@
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
+// The ccs, multifolding and util modules import from core and alloc instead of std, but there is
+// no no_std build mode: the sumcheck and the transcript come from the hyperplonk crates, rayon is
+// a hard dependency of the parallel iterators, and thiserror 1.x derives std::error::Error, all of
+// which need std. A `std` feature would have nothing to turn off until those are replaced.
+extern crate alloc;
+
+pub mod accumulator;
 pub mod ccs;
 pub mod multifolding;
+#[cfg(test)]
+mod test_vectors;
 
 pub mod espresso;
 pub mod util;
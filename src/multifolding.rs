@@ -1,63 +1,170 @@
 use ark_ec::CurveGroup;
 use ark_ff::Field;
 use ark_std::{One, Zero};
-use std::ops::Add;
+use core::ops::Add;
 
+use subroutines::poly_iop::prelude::PolyIOPErrors;
 use subroutines::PolyIOP;
+use thiserror::Error;
 use transcript::IOPTranscript;
 
 use crate::ccs::cccs::{Witness, CCCS};
-use crate::ccs::ccs::CCS;
+use crate::ccs::ccs::{CCSError, CCS};
+use crate::ccs::commitment::CommitmentScheme;
 use crate::ccs::lcccs::LCCCS;
-use crate::ccs::pedersen::Commitment;
-use crate::ccs::util::compute_all_sum_Mz_evals;
+use crate::ccs::pedersen::Pedersen;
+use crate::ccs::util::PreparedWitness;
 use crate::espresso::sum_check::structs::IOPProof as SumCheckProof;
 use crate::espresso::sum_check::{verifier::interpolate_uni_poly, SumCheck};
 use crate::espresso::virtual_polynomial::{eq_eval, VPAuxInfo, VirtualPolynomial};
+use crate::util::field::pow;
 use crate::util::hypercube::BooleanHypercube;
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+
+#[derive(Error, Debug)]
+pub enum MultifoldingError {
+    #[error("At least one LCCCS and one CCCS instance are needed")]
+    EmptyInstances,
+    #[error("Got {witnesses} witnesses for {instances} instances")]
+    WitnessCountMismatch { instances: usize, witnesses: usize },
+    #[error("Got {proofs} proofs to fold {instances} CCCS instances")]
+    ProofCountMismatch { instances: usize, proofs: usize },
+    #[error("Sumcheck error: {0}")]
+    SumCheck(PolyIOPErrors),
+    #[error("Sumcheck claim does not match the value computed from sigmas and thetas")]
+    SumCheckClaimMismatch,
+    #[error(
+        "The sum of g(x) over the boolean hypercube does not match the v values of the instances"
+    )]
+    ProverSumMismatch,
+    #[error("CCS error: {0}")]
+    CCS(#[from] CCSError),
+}
+
+/// The errors of the multifolding verifier, which rejects a proof through the sumcheck errors, a
+/// claim mismatch, or the CCS errors of malformed instances or sigmas and thetas
+pub type VerifierError = MultifoldingError;
+
+impl From<PolyIOPErrors> for MultifoldingError {
+    fn from(e: PolyIOPErrors) -> Self {
+        MultifoldingError::SumCheck(e)
+    }
+}
 
 /// Proof defines a multifolding proof
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Proof<C: CurveGroup> {
     pub sc_proof: SumCheckProof<C::ScalarField>,
     pub sigmas: Vec<Vec<C::ScalarField>>,
     pub thetas: Vec<Vec<C::ScalarField>>,
 }
 
+/// The values the instances are folded with, which come out of the same sumcheck: the point r_x'
+/// where it ends, and the sigmas and thetas evaluations of the LCCCS and CCCS instances at r_x'
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FoldProof<C: CurveGroup> {
+    pub sigmas: Vec<Vec<C::ScalarField>>,
+    pub thetas: Vec<Vec<C::ScalarField>>,
+    pub r_x_prime: Vec<C::ScalarField>,
+}
+
+/// The challenges the instances are folded with: rho_main weights the commitments, the u and the
+/// x of the instances, and rho_v weights their v values (the sigmas and thetas)
+///
+/// The folded instance is only satisfied by the witnesses folded with rho_main when both
+/// challenges are the same, since v_j is then the evaluation of the folded z vector. Distinct
+/// challenges are meant for the analysis of variants of the protocol.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct FoldChallenges<C: CurveGroup> {
+    pub rho_main: C::ScalarField,
+    pub rho_v: C::ScalarField,
+}
+
+impl<C: CurveGroup> FoldChallenges<C> {
+    /// The challenges of the HyperNova fold, where everything is weighted by the same rho
+    pub fn single(rho: C::ScalarField) -> Self {
+        FoldChallenges {
+            rho_main: rho,
+            rho_v: rho,
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct Multifolding<C: CurveGroup> {
+pub struct Multifolding<C: CurveGroup, CS: CommitmentScheme<C> = Pedersen<C>> {
     pub _c: PhantomData<C>,
+    pub _cs: PhantomData<CS>,
 }
 
-impl<C: CurveGroup> Multifolding<C> {
+impl<C: CurveGroup, CS: CommitmentScheme<C>> Multifolding<C, CS> {
     /// Compute the arrays of sigma_i and theta_i from step 4 corresponding to the LCCCS and CCCS
     /// instances
+    ///
+    /// There is one sigma_i for each of the μ LCCCS z vectors and one theta_i for each of the ν CCCS
+    /// z vectors, each of them being the vector of evaluations \sum_y M_j(r_x', y) z(y) for all j.
+    ///
+    /// Return an error if a z vector is not of length n or r_x' is not of length s.
+    #[allow(clippy::type_complexity)]
     pub fn compute_sigmas_and_thetas(
-        ccs: &CCS<C>,
+        ccs: &CCS<C::ScalarField>,
         z_lcccs: &[Vec<C::ScalarField>],
         z_cccs: &[Vec<C::ScalarField>],
         r_x_prime: &[C::ScalarField],
-    ) -> (Vec<Vec<C::ScalarField>>, Vec<Vec<C::ScalarField>>) {
+    ) -> Result<(Vec<Vec<C::ScalarField>>, Vec<Vec<C::ScalarField>>), CCSError> {
+        if r_x_prime.len() != ccs.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: ccs.s,
+                got: r_x_prime.len(),
+            });
+        }
+        let evaluate = |z: &Vec<C::ScalarField>| -> Result<Vec<C::ScalarField>, CCSError> {
+            let vec_Mz = ccs.compute_Mz_vectors(z)?;
+            PreparedWitness::from_Mz_vectors(vec_Mz, ccs.s).evaluate(r_x_prime)
+        };
         let mut sigmas: Vec<Vec<C::ScalarField>> = Vec::new();
         for z_lcccs_i in z_lcccs {
             // sigmas
-            let sigma_i = compute_all_sum_Mz_evals(&ccs.M, z_lcccs_i, r_x_prime, ccs.s_prime);
-            sigmas.push(sigma_i);
+            sigmas.push(evaluate(z_lcccs_i)?);
         }
         let mut thetas: Vec<Vec<C::ScalarField>> = Vec::new();
         for z_cccs_i in z_cccs {
             // thetas
-            let theta_i = compute_all_sum_Mz_evals(&ccs.M, z_cccs_i, r_x_prime, ccs.s_prime);
-            thetas.push(theta_i);
+            thetas.push(evaluate(z_cccs_i)?);
+        }
+        Ok((sigmas, thetas))
+    }
+
+    /// Compute the sigmas and thetas like [`Self::compute_sigmas_and_thetas`], out of the M_j * z
+    /// vectors of the LCCCS z vectors precomputed by [`PreparedWitness`]. In an accumulation loop
+    /// where the running instance does not change between several folds, its M_j * z products are
+    /// computed only once, and only the ones of the new CCCS z vectors are computed on each fold.
+    ///
+    /// The prepared witnesses must come from the matrices of the same CCS.
+    #[allow(clippy::type_complexity)]
+    pub fn compute_sigmas_and_thetas_prepared(
+        ccs: &CCS<C::ScalarField>,
+        prepared_lcccs: &[PreparedWitness<C::ScalarField>],
+        z_cccs: &[Vec<C::ScalarField>],
+        r_x_prime: &[C::ScalarField],
+    ) -> Result<(Vec<Vec<C::ScalarField>>, Vec<Vec<C::ScalarField>>), CCSError> {
+        if r_x_prime.len() != ccs.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: ccs.s,
+                got: r_x_prime.len(),
+            });
         }
-        (sigmas, thetas)
+        let sigmas: Vec<Vec<C::ScalarField>> = prepared_lcccs
+            .iter()
+            .map(|prepared| prepared.evaluate(r_x_prime))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (_, thetas) = Self::compute_sigmas_and_thetas(ccs, &[], z_cccs, r_x_prime)?;
+        Ok((sigmas, thetas))
     }
 
     /// Compute the right-hand-side of step 5 of the multifolding scheme
     pub fn compute_c_from_sigmas_and_thetas(
-        ccs: &CCS<C>,
+        ccs: &CCS<C::ScalarField>,
         vec_sigmas: &[Vec<C::ScalarField>],
         vec_thetas: &[Vec<C::ScalarField>],
         gamma: C::ScalarField,
@@ -97,19 +204,49 @@ impl<C: CurveGroup> Multifolding<C> {
         c
     }
 
-    /// Compute g(x) polynomial for the given inputs.
+    /// Return the evaluation g(r_x') that the sumcheck must end with for the instances to fold
+    /// correctly, computed from the sigmas, thetas and r_x' of the fold proof:
+    ///
+    /// c = \sum_{i=1}^{μ} e_i * \sum_{j=1}^{t} gamma^{(i-1)*t + j-1} * sigma_{i,j}
+    ///   + e_2 * \sum_{k=1}^{ν} gamma^{μ*t + k-1} * \sum_{i=1}^{q} c_i * \prod_{j \in S_i} theta_{k,j}
+    ///
+    /// where e_i = eq(r_x_i, r_x') for the r_x_i of the i-th LCCCS and e_2 = eq(beta, r_x').
+    ///
+    /// This is the value the verifier compares to the final claim of the sumcheck, so comparing it
+    /// with the evaluation coming out of the sumcheck tells whether a failing fold comes from the
+    /// sumcheck or from wrong sigmas and thetas.
+    pub fn claimed_eval(
+        lcccs: &[LCCCS<C, CS>],
+        cccs: &[CCCS<C, CS>],
+        fold_proof: &FoldProof<C>,
+        gamma: C::ScalarField,
+        beta: &[C::ScalarField],
+    ) -> C::ScalarField {
+        Self::compute_c_from_sigmas_and_thetas(
+            &cccs[0].ccs,
+            &fold_proof.sigmas,
+            &fold_proof.thetas,
+            gamma,
+            beta,
+            &lcccs.iter().map(|lcccs_i| lcccs_i.r_x.clone()).collect(),
+            &fold_proof.r_x_prime,
+        )
+    }
+
+    /// Compute g(x) polynomial for the given inputs, or an error if a z vector does not fit the
+    /// CCS of its instance.
     pub fn compute_g(
-        running_instances: &[LCCCS<C>],
-        cccs_instances: &[CCCS<C>],
+        running_instances: &[LCCCS<C, CS>],
+        cccs_instances: &[CCCS<C, CS>],
         z_lcccs: &[Vec<C::ScalarField>],
         z_cccs: &[Vec<C::ScalarField>],
         gamma: C::ScalarField,
         beta: &[C::ScalarField],
-    ) -> VirtualPolynomial<C::ScalarField> {
+    ) -> Result<VirtualPolynomial<C::ScalarField>, CCSError> {
         let mu = running_instances.len();
         let mut vec_Ls: Vec<VirtualPolynomial<C::ScalarField>> = Vec::new();
         for (i, running_instance) in running_instances.iter().enumerate() {
-            let mut Ls = running_instance.compute_Ls(&z_lcccs[i]);
+            let mut Ls = running_instance.compute_Ls(&z_lcccs[i])?;
             vec_Ls.append(&mut Ls);
         }
         let mut vec_Q: Vec<VirtualPolynomial<C::ScalarField>> = Vec::new();
@@ -131,42 +268,123 @@ impl<C: CurveGroup> Multifolding<C> {
             Q_i.scalar_mul(&gamma_mut_i);
             g = g.add(Q_i);
         }
-        g
+        Ok(g)
     }
 
+    /// Fold the μ LCCCS and ν CCCS instances into a single LCCCS instance, where the i-th instance
+    /// is weighted by rho^i
+    ///
+    /// The powers of rho are computed here out of the single folding challenge, so the instances
+    /// can't be folded with inconsistent challenges.
+    ///
+    /// This only touches the instances (the commitments are folded homomorphically), so it is the
+    /// fold performed by the verifier. The prover additionally folds the witnesses with
+    /// [`Self::fold_witness`].
+    ///
+    /// Return `CCSError::NoLCCCSInstances` if there is no LCCCS instance to fold into,
+    /// `CCSError::IncompatibleCCS` if the instances do not share the same CCS structure, and an
+    /// error if the lengths of r_x', of their public inputs/outputs or of the sigmas and thetas do
+    /// not match it, instead of folding truncated vectors.
     pub fn fold(
-        lcccs: &[LCCCS<C>],
-        cccs: &[CCCS<C>],
-        sigmas: &[Vec<C::ScalarField>],
-        thetas: &[Vec<C::ScalarField>],
-        r_x_prime: Vec<C::ScalarField>,
+        lcccs: &[LCCCS<C, CS>],
+        cccs: &[CCCS<C, CS>],
+        fold_proof: &FoldProof<C>,
         rho: C::ScalarField,
-    ) -> LCCCS<C> {
-        let mut C_folded = C::zero();
+    ) -> Result<LCCCS<C, CS>, CCSError> {
+        Self::fold_with_rho_powers(lcccs, cccs, fold_proof, rho)
+    }
+
+    /// Fold the instances weighting the i-th one by base_rho^i, the powers being computed here
+    /// out of the single challenge base_rho. This is the fold of [`Self::fold`], under a name
+    /// that says how the per-instance challenges are derived.
+    pub fn fold_with_rho_powers(
+        lcccs: &[LCCCS<C, CS>],
+        cccs: &[CCCS<C, CS>],
+        fold_proof: &FoldProof<C>,
+        base_rho: C::ScalarField,
+    ) -> Result<LCCCS<C, CS>, CCSError> {
+        Self::fold_with_challenges(lcccs, cccs, fold_proof, &FoldChallenges::single(base_rho))
+    }
+
+    /// Fold the instances as [`Self::fold`] does, weighting the commitments, u and x by the
+    /// powers of `challenges.rho_main` and the v values by the powers of `challenges.rho_v`
+    pub fn fold_with_challenges(
+        lcccs: &[LCCCS<C, CS>],
+        cccs: &[CCCS<C, CS>],
+        fold_proof: &FoldProof<C>,
+        challenges: &FoldChallenges<C>,
+    ) -> Result<LCCCS<C, CS>, CCSError> {
+        let ccs = &lcccs.first().ok_or(CCSError::NoLCCCSInstances)?.ccs;
+        if lcccs.iter().any(|lcccs_i| &lcccs_i.ccs != ccs)
+            || cccs.iter().any(|cccs_i| &cccs_i.ccs != ccs)
+        {
+            return Err(CCSError::IncompatibleCCS);
+        }
+        // r_x' becomes the r_x of the folded instance
+        if fold_proof.r_x_prime.len() != ccs.s {
+            return Err(CCSError::InvalidChallengeLength {
+                expected: ccs.s,
+                got: fold_proof.r_x_prime.len(),
+            });
+        }
+        let x_lengths = lcccs
+            .iter()
+            .map(|lcccs_i| lcccs_i.x.len())
+            .chain(cccs.iter().map(|cccs_i| cccs_i.x.len()));
+        if let Some(got) = x_lengths.find(|&len| len != ccs.l) {
+            return Err(CCSError::InvalidPublicInputLength {
+                expected: ccs.l,
+                got,
+            });
+        }
+        if fold_proof.sigmas.len() != lcccs.len() {
+            return Err(CCSError::InvalidNumberOfSigmas {
+                mu: lcccs.len(),
+                got: fold_proof.sigmas.len(),
+            });
+        }
+        if fold_proof.thetas.len() != cccs.len() {
+            return Err(CCSError::InvalidNumberOfThetas {
+                nu: cccs.len(),
+                got: fold_proof.thetas.len(),
+            });
+        }
+        let v_lengths = fold_proof.sigmas.iter().chain(fold_proof.thetas.iter());
+        if let Some(got) = v_lengths.map(|v| v.len()).find(|&len| len != ccs.t) {
+            return Err(CCSError::SigmaThetaLengthMismatch { t: ccs.t, got });
+        }
+
+        let mut C_folded: Option<CS::Commitment> = None;
         let mut u_folded = C::ScalarField::zero();
         let mut x_folded: Vec<C::ScalarField> = vec![C::ScalarField::zero(); lcccs[0].x.len()];
-        let mut v_folded: Vec<C::ScalarField> = vec![C::ScalarField::zero(); sigmas[0].len()];
+        let mut v_folded: Vec<C::ScalarField> =
+            vec![C::ScalarField::zero(); fold_proof.sigmas[0].len()];
 
         for i in 0..(lcccs.len() + cccs.len()) {
-            let rho_i = rho.pow([i as u64]);
+            let rho_i = pow(challenges.rho_main, i as u64);
+            let rho_v_i = pow(challenges.rho_v, i as u64);
 
-            let c: C;
+            let c: &CS::Commitment;
             let u: C::ScalarField;
             let x: Vec<C::ScalarField>;
             let v: Vec<C::ScalarField>;
             if i < lcccs.len() {
-                c = lcccs[i].C.0;
+                c = &lcccs[i].C;
                 u = lcccs[i].u;
                 x = lcccs[i].x.clone();
-                v = sigmas[i].clone();
+                v = fold_proof.sigmas[i].clone();
             } else {
-                c = cccs[i - lcccs.len()].C.0;
+                c = &cccs[i - lcccs.len()].C;
                 u = C::ScalarField::one();
                 x = cccs[i - lcccs.len()].x.clone();
-                v = thetas[i - lcccs.len()].clone();
+                v = fold_proof.thetas[i - lcccs.len()].clone();
             }
 
-            C_folded += c.mul(rho_i);
+            let c_rho_i = CS::scalar_mul(c, &rho_i);
+            C_folded = Some(match C_folded {
+                Some(acc) => CS::add(&acc, &c_rho_i),
+                None => c_rho_i,
+            });
             u_folded += rho_i * u;
             x_folded = x_folded
                 .iter()
@@ -182,23 +400,44 @@ impl<C: CurveGroup> Multifolding<C> {
                 .iter()
                 .zip(
                     v.iter()
-                        .map(|x_i| *x_i * rho_i)
+                        .map(|x_i| *x_i * rho_v_i)
                         .collect::<Vec<C::ScalarField>>(),
                 )
                 .map(|(a_i, b_i)| *a_i + b_i)
                 .collect();
         }
 
-        LCCCS::<C> {
-            C: Commitment(C_folded),
-            ccs: lcccs[0].ccs.clone(),
+        Ok(LCCCS::<C, CS> {
+            // there is always at least one instance to fold, so the commitment is set
+            C: C_folded.unwrap(),
+            ccs: ccs.clone(),
             u: u_folded,
             x: x_folded,
-            r_x: r_x_prime,
+            r_x: fold_proof.r_x_prime.clone(),
             v: v_folded,
-        }
+        })
     }
 
+    /// Fold the instances out of the sigmas, thetas and r_x' passed separately
+    #[deprecated(note = "bundle the sigmas, thetas and r_x' into a FoldProof and use fold")]
+    pub fn fold_unbundled(
+        lcccs: &[LCCCS<C, CS>],
+        cccs: &[CCCS<C, CS>],
+        sigmas: &[Vec<C::ScalarField>],
+        thetas: &[Vec<C::ScalarField>],
+        r_x_prime: Vec<C::ScalarField>,
+        rho: C::ScalarField,
+    ) -> Result<LCCCS<C, CS>, CCSError> {
+        let fold_proof = FoldProof::<C> {
+            sigmas: sigmas.to_vec(),
+            thetas: thetas.to_vec(),
+            r_x_prime,
+        };
+        Self::fold(lcccs, cccs, &fold_proof, rho)
+    }
+
+    /// Fold the witnesses of the μ LCCCS and ν CCCS instances, following the same rho^i
+    /// weighting as [`Self::fold`]
     pub fn fold_witness(
         w_lcccs: &[Witness<C::ScalarField>],
         w_cccs: &[Witness<C::ScalarField>],
@@ -208,7 +447,7 @@ impl<C: CurveGroup> Multifolding<C> {
         let mut r_w_folded = C::ScalarField::zero();
 
         for i in 0..(w_lcccs.len() + w_cccs.len()) {
-            let rho_i = rho.pow([i as u64]);
+            let rho_i = pow(rho, i as u64);
             let w: Vec<C::ScalarField>;
             let r_w: C::ScalarField;
 
@@ -238,47 +477,131 @@ impl<C: CurveGroup> Multifolding<C> {
         }
     }
 
+    /// Absorb the CCS structure, through its digest, and the LCCCS and CCCS instances into the
+    /// transcript, so that the challenges gamma, beta, r_x' and rho depend on the CCS and the
+    /// instances being folded.
+    fn absorb_instances(
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        ccs_digest: &[u8; 32],
+        running_instances: &[LCCCS<C, CS>],
+        new_instances: &[CCCS<C, CS>],
+    ) {
+        CCS::<C::ScalarField>::absorb_digest(transcript, ccs_digest);
+        for lcccs in running_instances {
+            transcript
+                .append_serializable_element(b"lcccs C", &lcccs.C)
+                .unwrap();
+            transcript
+                .append_field_element(b"lcccs u", &lcccs.u)
+                .unwrap();
+            transcript
+                .append_serializable_element(b"lcccs x", &lcccs.x)
+                .unwrap();
+            transcript
+                .append_serializable_element(b"lcccs r_x", &lcccs.r_x)
+                .unwrap();
+            transcript
+                .append_serializable_element(b"lcccs v", &lcccs.v)
+                .unwrap();
+        }
+        for cccs in new_instances {
+            transcript
+                .append_serializable_element(b"cccs C", &cccs.C)
+                .unwrap();
+            transcript
+                .append_serializable_element(b"cccs x", &cccs.x)
+                .unwrap();
+        }
+    }
+
+    /// Absorb the sigmas and thetas into the transcript before squeezing rho
+    fn absorb_sigmas_and_thetas(
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        sigmas: &[Vec<C::ScalarField>],
+        thetas: &[Vec<C::ScalarField>],
+    ) {
+        for sigma in sigmas {
+            transcript
+                .append_serializable_element(b"sigma", sigma)
+                .unwrap();
+        }
+        for theta in thetas {
+            transcript
+                .append_serializable_element(b"theta", theta)
+                .unwrap();
+        }
+    }
+
     /// Perform the multifolding prover.
     ///
     /// Given μ LCCCS instances and ν CCS instances, fold them into a single LCCCS instance. Since
     /// this is the prover, also fold their witness.
     ///
     /// Return the final folded LCCCS, the folded witness, the sumcheck proof, and the helper
-    /// sumcheck claims sigmas and thetas.
+    /// sumcheck claims sigmas and thetas, or an error if there are no instances, the witnesses do
+    /// not match the instances, or the instances do not share the same CCS.
+    #[allow(clippy::type_complexity)]
     pub fn prove(
         transcript: &mut IOPTranscript<C::ScalarField>,
-        running_instances: &[LCCCS<C>],
-        new_instances: &[CCCS<C>],
+        running_instances: &[LCCCS<C, CS>],
+        new_instances: &[CCCS<C, CS>],
         w_lcccs: &[Witness<C::ScalarField>],
         w_cccs: &[Witness<C::ScalarField>],
-    ) -> (Proof<C>, LCCCS<C>, Witness<C::ScalarField>) {
-        // TODO appends to transcript
+    ) -> Result<(Proof<C>, LCCCS<C, CS>, Witness<C::ScalarField>), MultifoldingError> {
+        let ccs_digest = running_instances
+            .first()
+            .ok_or(MultifoldingError::EmptyInstances)?
+            .ccs
+            .digest();
+        Self::prove_with_ccs_digest(
+            transcript,
+            &ccs_digest,
+            running_instances,
+            new_instances,
+            w_lcccs,
+            w_cccs,
+        )
+    }
+
+    /// Same as [`Self::prove`], but absorbing the given [`CCS::digest`] of the CCS of the
+    /// instances instead of hashing it again, so that a prover folding many times over the same
+    /// CCS, such as [`crate::accumulator::Accumulator`], only hashes it once. The digest is not
+    /// checked against the CCS: a wrong digest gives a proof that does not verify.
+    #[allow(clippy::type_complexity)]
+    pub fn prove_with_ccs_digest(
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        ccs_digest: &[u8; 32],
+        running_instances: &[LCCCS<C, CS>],
+        new_instances: &[CCCS<C, CS>],
+        w_lcccs: &[Witness<C::ScalarField>],
+        w_cccs: &[Witness<C::ScalarField>],
+    ) -> Result<(Proof<C>, LCCCS<C, CS>, Witness<C::ScalarField>), MultifoldingError> {
+        if running_instances.is_empty() || new_instances.is_empty() {
+            return Err(MultifoldingError::EmptyInstances);
+        }
+        for (instances, witnesses) in [
+            (running_instances.len(), w_lcccs.len()),
+            (new_instances.len(), w_cccs.len()),
+        ] {
+            if instances != witnesses {
+                return Err(MultifoldingError::WitnessCountMismatch {
+                    instances,
+                    witnesses,
+                });
+            }
+        }
 
-        assert!(!running_instances.is_empty());
-        assert!(!new_instances.is_empty());
+        Self::absorb_instances(transcript, ccs_digest, running_instances, new_instances);
 
         // construct the LCCCS z vector from the relaxation factor, public IO and witness
-        // XXX this deserves its own function in LCCCS
         let mut z_lcccs = Vec::new();
-        for (i, running_instance) in running_instances.iter().enumerate() {
-            let z_1: Vec<C::ScalarField> = [
-                vec![running_instance.u],
-                running_instance.x.clone(),
-                w_lcccs[i].w.to_vec(),
-            ]
-            .concat();
-            z_lcccs.push(z_1);
+        for (running_instance, w) in running_instances.iter().zip(w_lcccs) {
+            z_lcccs.push(running_instance.z(w)?);
         }
         // construct the CCCS z vector from the public IO and witness
         let mut z_cccs = Vec::new();
-        for (i, new_instance) in new_instances.iter().enumerate() {
-            let z_2: Vec<C::ScalarField> = [
-                vec![C::ScalarField::one()],
-                new_instance.x.clone(),
-                w_cccs[i].w.to_vec(),
-            ]
-            .concat();
-            z_cccs.push(z_2);
+        for (new_instance, w) in new_instances.iter().zip(w_cccs) {
+            z_cccs.push(new_instance.z(w)?);
         }
 
         // Step 1: Get some challenges
@@ -295,11 +618,11 @@ impl<C: CurveGroup> Multifolding<C> {
             &z_cccs,
             gamma,
             &beta,
-        );
+        )?;
 
         // Step 3: Run the sumcheck prover
         let sumcheck_proof =
-            <PolyIOP<C::ScalarField> as SumCheck<C::ScalarField>>::prove(&g, transcript).unwrap(); // XXX unwrap
+            <PolyIOP<C::ScalarField> as SumCheck<C::ScalarField>>::prove(&g, transcript)?;
 
         // Note: The following two "sanity checks" are done for this prototype, in a final version
         // they should be removed.
@@ -315,9 +638,9 @@ impl<C: CurveGroup> Multifolding<C> {
         // note: this is the sum of g(x) over the whole boolean hypercube
         let extracted_sum =
             <PolyIOP<C::ScalarField> as SumCheck<C::ScalarField>>::extract_sum(&sumcheck_proof);
-        assert_eq!(extracted_sum, g_over_bhc);
         // Sanity check 2: expect \sum v_j * gamma^j to be equal to the sum of g(x) over the
-        // boolean hypercube (and also equal to the extracted_sum from the SumCheck).
+        // boolean hypercube (and also equal to the extracted_sum from the SumCheck), which fails
+        // when the instances are not satisfied by the witnesses.
         let mut sum_v_j_gamma = C::ScalarField::zero();
         for (i, running_instance) in running_instances.iter().enumerate() {
             for j in 0..running_instance.v.len() {
@@ -325,8 +648,9 @@ impl<C: CurveGroup> Multifolding<C> {
                 sum_v_j_gamma += running_instance.v[j] * gamma_j;
             }
         }
-        assert_eq!(g_over_bhc, sum_v_j_gamma);
-        assert_eq!(extracted_sum, sum_v_j_gamma);
+        if extracted_sum != g_over_bhc || g_over_bhc != sum_v_j_gamma {
+            return Err(MultifoldingError::ProverSumMismatch);
+        }
         //////////////////////////////////////////////////////////////////////
 
         // Step 2: dig into the sumcheck and extract r_x_prime
@@ -338,50 +662,75 @@ impl<C: CurveGroup> Multifolding<C> {
             &z_lcccs,
             &z_cccs,
             &r_x_prime,
-        );
+        )?;
 
-        // Step 6: Get the folding challenge
+        // Step 6: Get the folding challenge, bound to the sigmas and thetas sent by the prover
+        Self::absorb_sigmas_and_thetas(transcript, &sigmas, &thetas);
         let rho: C::ScalarField = transcript.get_and_append_challenge(b"rho").unwrap();
 
         // Step 7: Create the folded instance
-        let folded_lcccs = Self::fold(
-            running_instances,
-            new_instances,
-            &sigmas,
-            &thetas,
+        let fold_proof = FoldProof::<C> {
+            sigmas,
+            thetas,
             r_x_prime,
-            rho,
-        );
+        };
+        let folded_lcccs = Self::fold(running_instances, new_instances, &fold_proof, rho)?;
 
         // Step 8: Fold the witnesses
         let folded_witness = Self::fold_witness(w_lcccs, w_cccs, rho);
 
-        (
+        Ok((
             Proof::<C> {
                 sc_proof: sumcheck_proof,
-                sigmas,
-                thetas,
+                sigmas: fold_proof.sigmas,
+                thetas: fold_proof.thetas,
             },
             folded_lcccs,
             folded_witness,
-        )
+        ))
     }
 
     /// Perform the multifolding verifier:
     ///
-    /// Given μ LCCCS instances and ν CCS instances, fold them into a single LCCCS instance.
+    /// Given μ LCCCS instances and ν CCS instances, fold them into a single LCCCS instance. The
+    /// challenges r_x' and rho are derived from the transcript, so the verifier's transcript must
+    /// be in the same state as the prover's one.
     ///
-    /// Return the folded LCCCS instance.
+    /// Return the folded LCCCS instance, or an error if the proof does not verify.
     pub fn verify(
         transcript: &mut IOPTranscript<C::ScalarField>,
-        running_instances: &[LCCCS<C>],
-        new_instances: &[CCCS<C>],
+        running_instances: &[LCCCS<C, CS>],
+        new_instances: &[CCCS<C, CS>],
         proof: Proof<C>,
-    ) -> LCCCS<C> {
-        // TODO appends to transcript
+    ) -> Result<LCCCS<C, CS>, VerifierError> {
+        let ccs_digest = running_instances
+            .first()
+            .ok_or(MultifoldingError::EmptyInstances)?
+            .ccs
+            .digest();
+        Self::verify_with_ccs_digest(
+            transcript,
+            &ccs_digest,
+            running_instances,
+            new_instances,
+            proof,
+        )
+    }
+
+    /// Same as [`Self::verify`], but absorbing the given [`CCS::digest`] of the CCS of the
+    /// instances instead of hashing it again, see [`Self::prove_with_ccs_digest`]
+    pub fn verify_with_ccs_digest(
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        ccs_digest: &[u8; 32],
+        running_instances: &[LCCCS<C, CS>],
+        new_instances: &[CCCS<C, CS>],
+        proof: Proof<C>,
+    ) -> Result<LCCCS<C, CS>, VerifierError> {
+        if running_instances.is_empty() || new_instances.is_empty() {
+            return Err(MultifoldingError::EmptyInstances);
+        }
 
-        assert!(!running_instances.is_empty());
-        assert!(!new_instances.is_empty());
+        Self::absorb_instances(transcript, ccs_digest, running_instances, new_instances);
 
         // Step 1: Get some challenges
         let gamma: C::ScalarField = transcript.get_and_append_challenge(b"gamma").unwrap();
@@ -411,60 +760,94 @@ impl<C: CurveGroup> Multifolding<C> {
             &proof.sc_proof,
             &vp_aux_info,
             transcript,
-        )
-        .unwrap();
+        )?;
 
         // Step 2: Dig into the sumcheck claim and extract the randomness used
-        let r_x_prime = sumcheck_subclaim.point.clone();
+        let fold_proof = FoldProof::<C> {
+            sigmas: proof.sigmas,
+            thetas: proof.thetas,
+            r_x_prime: sumcheck_subclaim.point.clone(),
+        };
 
         // Step 5: Finish verifying sumcheck (verify the claim c)
-        let c = Self::compute_c_from_sigmas_and_thetas(
-            &new_instances[0].ccs,
-            &proof.sigmas,
-            &proof.thetas,
-            gamma,
-            &beta,
-            &running_instances
-                .iter()
-                .map(|lcccs| lcccs.r_x.clone())
-                .collect(),
-            &r_x_prime,
-        );
+        let c = Self::claimed_eval(running_instances, new_instances, &fold_proof, gamma, &beta);
         // check that the g(r_x') from the sumcheck proof is equal to the computed c from sigmas&thetas
-        assert_eq!(c, sumcheck_subclaim.expected_evaluation);
+        if c != sumcheck_subclaim.expected_evaluation {
+            return Err(MultifoldingError::SumCheckClaimMismatch);
+        }
 
         // Sanity check: we can also compute g(r_x') from the proof last evaluation value, and
         // should be equal to the previously obtained values.
         let g_on_rxprime_from_sumcheck_last_eval = interpolate_uni_poly::<C::ScalarField>(
             &proof.sc_proof.proofs.last().unwrap().evaluations,
-            *r_x_prime.last().unwrap(),
-        )
-        .unwrap();
-        assert_eq!(g_on_rxprime_from_sumcheck_last_eval, c);
-        assert_eq!(
-            g_on_rxprime_from_sumcheck_last_eval,
-            sumcheck_subclaim.expected_evaluation
-        );
+            *fold_proof.r_x_prime.last().unwrap(),
+        )?;
+        if g_on_rxprime_from_sumcheck_last_eval != c {
+            return Err(MultifoldingError::SumCheckClaimMismatch);
+        }
 
-        // Step 6: Get the folding challenge
+        // Step 6: Get the folding challenge, bound to the sigmas and thetas sent by the prover
+        Self::absorb_sigmas_and_thetas(transcript, &fold_proof.sigmas, &fold_proof.thetas);
         let rho: C::ScalarField = transcript.get_and_append_challenge(b"rho").unwrap();
 
         // Step 7: Compute the folded instance
-        Self::fold(
+        Ok(Self::fold(
             running_instances,
             new_instances,
-            &proof.sigmas,
-            &proof.thetas,
-            r_x_prime,
+            &fold_proof,
             rho,
-        )
+        )?)
+    }
+
+    /// Verify a sequence of folds, folding each of the CCCS instances in turn into the LCCCS
+    /// accumulated so far, starting from running_instance, with the proof of the same index.
+    ///
+    /// The same transcript goes through all the steps, so each step's challenges are derived
+    /// after the previous step's ones, and the proofs must come from a prover folding the
+    /// instances in the same order with a transcript in the same initial state.
+    ///
+    /// Return the final accumulated LCCCS instance, or an error if any step does not verify.
+    pub fn fold_all(
+        transcript: &mut IOPTranscript<C::ScalarField>,
+        running_instance: &LCCCS<C, CS>,
+        new_instances: &[CCCS<C, CS>],
+        proofs: Vec<Proof<C>>,
+    ) -> Result<LCCCS<C, CS>, VerifierError> {
+        if new_instances.is_empty() {
+            return Err(MultifoldingError::EmptyInstances);
+        }
+        if proofs.len() != new_instances.len() {
+            return Err(MultifoldingError::ProofCountMismatch {
+                instances: new_instances.len(),
+                proofs: proofs.len(),
+            });
+        }
+
+        // folding keeps the CCS of the running instance, so it is hashed once for all the steps
+        let ccs_digest = running_instance.ccs.digest();
+        let mut folded_lcccs = running_instance.clone();
+        for (new_instance, proof) in new_instances.iter().zip(proofs) {
+            folded_lcccs = Self::verify_with_ccs_digest(
+                transcript,
+                &ccs_digest,
+                &[folded_lcccs],
+                &[new_instance.clone()],
+                proof,
+            )?;
+        }
+        Ok(folded_lcccs)
     }
 }
 
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use crate::ccs::ccs::test::{get_test_ccs, get_test_z};
+    use crate::ccs::ccs::test::{
+        get_random_ccs, get_random_z, get_test_ccs, get_test_ccs_cubic, get_test_z,
+        get_test_z_cubic,
+    };
+    use alloc::sync::Arc;
+    use ark_std::rand::Rng;
     use ark_std::test_rng;
     use ark_std::UniformRand;
 
@@ -476,7 +859,7 @@ pub mod test {
 
     #[test]
     fn test_compute_sigmas_and_thetas() -> () {
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z1 = get_test_z(3);
         let z2 = get_test_z(4);
         ccs.check_relation(&z1).unwrap();
@@ -488,16 +871,18 @@ pub mod test {
         let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
 
         // Initialize a multifolding object
-        let pedersen_params = Pedersen::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (lcccs_instance, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1);
-        let (cccs_instance, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z2);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs_instance, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1).unwrap();
+        let (cccs_instance, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z2).unwrap();
 
         let (sigmas, thetas) = NIMFS::compute_sigmas_and_thetas(
             &lcccs_instance.ccs,
             &vec![z1.clone()],
             &vec![z2.clone()],
             &r_x_prime,
-        );
+        )
+        .unwrap();
 
         let g = NIMFS::compute_g(
             &vec![lcccs_instance.clone()],
@@ -506,7 +891,8 @@ pub mod test {
             &vec![z2.clone()],
             gamma,
             &beta,
-        );
+        )
+        .unwrap();
 
         // we expect g(r_x_prime) to be equal to:
         // c = (sum gamma^j * e1 * sigma_j) + gamma^{t+1} * e2 * sum c_i * prod theta_j
@@ -524,9 +910,124 @@ pub mod test {
         assert_eq!(c, expected_c);
     }
 
+    /// Computing the sigmas out of the prepared LCCCS witnesses gives the same sigmas and thetas as
+    /// computing them from the z vectors
+    #[test]
+    fn test_compute_sigmas_and_thetas_prepared() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z_lcccs: Vec<Vec<Fr>> = (0..2).map(|i| get_test_z(i + 3)).collect();
+        let z_cccs: Vec<Vec<Fr>> = (0..3).map(|i| get_test_z(i + 5)).collect();
+        let prepared_lcccs: Vec<PreparedWitness<Fr>> = z_lcccs
+            .iter()
+            .map(|z| PreparedWitness::new(&ccs.M, z, ccs.s))
+            .collect();
+
+        // the same prepared witnesses are reused for several folds
+        for _ in 0..3 {
+            let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+            assert_eq!(
+                NIMFS::compute_sigmas_and_thetas_prepared(
+                    &ccs,
+                    &prepared_lcccs,
+                    &z_cccs,
+                    &r_x_prime
+                )
+                .unwrap(),
+                NIMFS::compute_sigmas_and_thetas(&ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap()
+            );
+        }
+
+        assert!(matches!(
+            NIMFS::compute_sigmas_and_thetas_prepared(&ccs, &prepared_lcccs, &z_cccs, &[]),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 0
+            })
+        ));
+    }
+
+    /// The claimed evaluation is the evaluation of g(x) at r_x', and it changes when a sigma is
+    /// wrong
+    #[test]
+    fn test_claimed_eval() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let z1 = get_test_z(3);
+        let z2 = get_test_z(4);
+        let gamma: Fr = Fr::rand(&mut rng);
+        let beta: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1).unwrap();
+        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z2).unwrap();
+
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &[z1.clone()], &[z2.clone()], &r_x_prime)
+                .unwrap();
+        let mut fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime: r_x_prime.clone(),
+        };
+        let g = NIMFS::compute_g(
+            &[lcccs.clone()],
+            &[cccs.clone()],
+            &[z1],
+            &[z2],
+            gamma,
+            &beta,
+        )
+        .unwrap();
+
+        let claimed =
+            NIMFS::claimed_eval(&[lcccs.clone()], &[cccs.clone()], &fold_proof, gamma, &beta);
+        assert_eq!(claimed, g.evaluate(&r_x_prime).unwrap());
+
+        fold_proof.sigmas[0][1] += Fr::one();
+        let bad_claimed = NIMFS::claimed_eval(&[lcccs], &[cccs], &fold_proof, gamma, &beta);
+        assert_ne!(bad_claimed, claimed);
+    }
+
+    /// Computing the sigmas and thetas of μ LCCCS and ν CCCS z vectors at once gives the same
+    /// result as computing them for one LCCCS and one CCCS z vector at a time
+    #[test]
+    fn test_compute_sigmas_and_thetas_mu_nu() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+        let z_lcccs: Vec<Vec<Fr>> = (0..2).map(|i| get_test_z(i + 3)).collect();
+        let z_cccs: Vec<Vec<Fr>> = (0..3).map(|i| get_test_z(i + 5)).collect();
+
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap();
+        assert_eq!(sigmas.len(), z_lcccs.len());
+        assert_eq!(thetas.len(), z_cccs.len());
+
+        for (i, z_lcccs_i) in z_lcccs.iter().enumerate() {
+            for (j, z_cccs_j) in z_cccs.iter().enumerate() {
+                let (sigma, theta) = NIMFS::compute_sigmas_and_thetas(
+                    &ccs,
+                    &[z_lcccs_i.clone()],
+                    &[z_cccs_j.clone()],
+                    &r_x_prime,
+                )
+                .unwrap();
+                assert_eq!(sigma[0], sigmas[i]);
+                assert_eq!(theta[0], thetas[j]);
+            }
+        }
+    }
+
     #[test]
     fn test_compute_g() -> () {
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z1 = get_test_z(3);
         let z2 = get_test_z(4);
         ccs.check_relation(&z1).unwrap();
@@ -537,9 +1038,10 @@ pub mod test {
         let beta: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
 
         // Initialize a multifolding object
-        let pedersen_params = Pedersen::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (lcccs_instance, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1);
-        let (cccs_instance, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z2);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (lcccs_instance, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1).unwrap();
+        let (cccs_instance, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z2).unwrap();
 
         let mut sum_v_j_gamma = Fr::zero();
         for j in 0..lcccs_instance.v.len() {
@@ -555,7 +1057,8 @@ pub mod test {
             &vec![z2.clone()],
             gamma,
             &beta,
-        );
+        )
+        .unwrap();
 
         // evaluate g(x) over x \in {0,1}^s
         let mut g_on_bhc = Fr::zero();
@@ -565,7 +1068,7 @@ pub mod test {
 
         // evaluate sum_{j \in [t]} (gamma^j * Lj(x)) over x \in {0,1}^s
         let mut sum_Lj_on_bhc = Fr::zero();
-        let vec_L = lcccs_instance.compute_Ls(&z1);
+        let vec_L = lcccs_instance.compute_Ls(&z1).unwrap();
         for x in BooleanHypercube::new(ccs.s).into_iter() {
             for j in 0..vec_L.len() {
                 let gamma_j = gamma.pow([j as u64]);
@@ -587,7 +1090,7 @@ pub mod test {
 
     #[test]
     fn test_fold() -> () {
-        let ccs = get_test_ccs();
+        let ccs = get_test_ccs::<Fr>();
         let z1 = get_test_z(3);
         let z2 = get_test_z(4);
         ccs.check_relation(&z1).unwrap();
@@ -597,20 +1100,23 @@ pub mod test {
         let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
 
         // Initialize a multifolding object
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
-        let (running_instance, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let (running_instance, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1).unwrap();
 
         let (sigmas, thetas) = Multifolding::<G1Projective>::compute_sigmas_and_thetas(
             &running_instance.ccs,
             &vec![z1.clone()],
             &vec![z2.clone()],
             &r_x_prime,
-        );
+        )
+        .unwrap();
 
-        let pedersen_params = Pedersen::<G1Projective>::new_params(&mut rng, ccs.n - ccs.l - 1);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
 
-        let (lcccs, w1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1);
-        let (cccs, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z2);
+        let (lcccs, w1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z1).unwrap();
+        let (cccs, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z2).unwrap();
 
         lcccs.check_relation(&pedersen_params, &w1).unwrap();
         cccs.check_relation(&pedersen_params, &w2).unwrap();
@@ -618,29 +1124,412 @@ pub mod test {
         let mut rng = test_rng();
         let rho = Fr::rand(&mut rng);
 
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime,
+        };
         let folded = Multifolding::<G1Projective>::fold(
+            &vec![lcccs.clone()],
+            &vec![cccs.clone()],
+            &fold_proof,
+            rho,
+        )
+        .unwrap();
+
+        // the deprecated fold with unbundled arguments gives the same instance
+        #[allow(deprecated)]
+        let folded_unbundled = Multifolding::<G1Projective>::fold_unbundled(
             &vec![lcccs],
             &vec![cccs],
-            &sigmas,
-            &thetas,
-            r_x_prime,
+            &fold_proof.sigmas,
+            &fold_proof.thetas,
+            fold_proof.r_x_prime.clone(),
             rho,
-        );
+        )
+        .unwrap();
+        assert_eq!(folded, folded_unbundled);
 
         let w_folded = Multifolding::<G1Projective>::fold_witness(&vec![w1], &vec![w2], rho);
 
+        // the instance-only fold of the commitments matches the commitment of the folded witness
+        assert_eq!(
+            folded.C,
+            Pedersen::<G1Projective>::commit(&pedersen_params, &w_folded.w, &w_folded.r_w).unwrap()
+        );
+
         // check lcccs relation
         folded.check_relation(&pedersen_params, &w_folded).unwrap();
     }
 
+    /// Folding one LCCCS and one CCCS instance weights them by rho^0 = 1 and rho^1 = rho, as in the
+    /// two-instance fold of the paper
+    #[test]
+    fn test_fold_rho_powers() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z_1 = get_test_z(3);
+        let z_2 = get_test_z(4);
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
+        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
+
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &[z_1], &[z_2], &r_x_prime).unwrap();
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime: r_x_prime.clone(),
+        };
+        let rho = Fr::rand(&mut rng);
+        let folded = NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &fold_proof, rho).unwrap();
+        assert_eq!(
+            NIMFS::fold_with_rho_powers(&[lcccs.clone()], &[cccs.clone()], &fold_proof, rho)
+                .unwrap(),
+            folded
+        );
+
+        assert_eq!(folded.C, lcccs.C.clone() + cccs.C.clone() * rho);
+        assert_eq!(folded.u, lcccs.u + rho);
+        assert_eq!(folded.x, vec![lcccs.x[0] + rho * cccs.x[0]]);
+        assert_eq!(folded.r_x, r_x_prime);
+        let expected_v: Vec<Fr> = fold_proof.sigmas[0]
+            .iter()
+            .zip(fold_proof.thetas[0].iter())
+            .map(|(sigma_j, theta_j)| *sigma_j + rho * theta_j)
+            .collect();
+        assert_eq!(folded.v, expected_v);
+    }
+
+    /// Fold 2 LCCCS and 3 CCCS instances at once, and check that the folded instance satisfies the
+    /// LCCCS relation with the folded witness
+    #[test]
+    fn test_fold_mu_nu() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+
+        let z_lcccs: Vec<Vec<Fr>> = (0..2).map(|i| get_test_z(i + 3)).collect();
+        let z_cccs: Vec<Vec<Fr>> = (0..3).map(|i| get_test_z(i + 5)).collect();
+
+        let mut lcccs_instances = Vec::new();
+        let mut w_lcccs = Vec::new();
+        for z in z_lcccs.iter() {
+            let (lcccs, w) = ccs.to_lcccs(&mut rng, &pedersen_params, z).unwrap();
+            lcccs_instances.push(lcccs);
+            w_lcccs.push(w);
+        }
+        let mut cccs_instances = Vec::new();
+        let mut w_cccs = Vec::new();
+        for z in z_cccs.iter() {
+            let (cccs, w) = ccs.to_cccs(&mut rng, &pedersen_params, z).unwrap();
+            cccs_instances.push(cccs);
+            w_cccs.push(w);
+        }
+
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap();
+
+        let rho = Fr::rand(&mut rng);
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime,
+        };
+        let folded = NIMFS::fold(&lcccs_instances, &cccs_instances, &fold_proof, rho).unwrap();
+        let w_folded = NIMFS::fold_witness(&w_lcccs, &w_cccs, rho);
+
+        folded.check_relation(&pedersen_params, &w_folded).unwrap();
+
+        // the folded witness is committed to by the folded commitment, but folding the witnesses
+        // with another rho breaks it
+        folded
+            .verify_fold_commitment(&pedersen_params, &w_folded)
+            .unwrap();
+        let bad_w_folded = NIMFS::fold_witness(&w_lcccs, &w_cccs, rho + Fr::one());
+        assert!(matches!(
+            folded.verify_fold_commitment(&pedersen_params, &bad_w_folded),
+            Err(CCSError::CommitmentMismatch)
+        ));
+    }
+
+    /// Property test of the fold arithmetic over random circuits, numbers of instances and
+    /// challenges: the folded instance is satisfied by the folded witness when the sigmas and
+    /// thetas are the evaluations at the r_x' the instances are folded at and the witnesses are
+    /// folded with the same rho as the instances, and it is not satisfied when any of these
+    /// challenges is swapped for an adversarial one.
+    #[test]
+    fn test_fold_adversarial_challenges() -> () {
+        let mut rng = test_rng();
+
+        for _ in 0..10 {
+            let l = rng.gen_range(1..4);
+            let gates = rng.gen_range(2..9);
+            let ccs = get_random_ccs::<Fr, _>(&mut rng, l, gates);
+            let pedersen_params =
+                Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+            let (mu, nu) = (rng.gen_range(1..4), rng.gen_range(1..4));
+            let mut random_z = || {
+                let x: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+                let z = get_random_z(&ccs, &x);
+                ccs.check_relation(&z).unwrap();
+                z
+            };
+            let z_lcccs: Vec<Vec<Fr>> = (0..mu).map(|_| random_z()).collect();
+            let z_cccs: Vec<Vec<Fr>> = (0..nu).map(|_| random_z()).collect();
+
+            let (lcccs_instances, w_lcccs): (Vec<_>, Vec<_>) = z_lcccs
+                .iter()
+                .map(|z| ccs.to_lcccs(&mut rng, &pedersen_params, z).unwrap())
+                .unzip();
+            let (cccs_instances, w_cccs): (Vec<_>, Vec<_>) = z_cccs
+                .iter()
+                .map(|z| ccs.to_cccs(&mut rng, &pedersen_params, z).unwrap())
+                .unzip();
+
+            let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+            let (sigmas, thetas) =
+                NIMFS::compute_sigmas_and_thetas(&ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap();
+            let fold_proof = FoldProof {
+                sigmas,
+                thetas,
+                r_x_prime,
+            };
+            let rho = Fr::rand(&mut rng);
+
+            // honest challenges
+            let folded = NIMFS::fold(&lcccs_instances, &cccs_instances, &fold_proof, rho).unwrap();
+            let w_folded = NIMFS::fold_witness(&w_lcccs, &w_cccs, rho);
+            folded.check_relation(&pedersen_params, &w_folded).unwrap();
+
+            // the instances and the witnesses are folded with different rhos
+            let bad_rho = Fr::rand(&mut rng);
+            let bad_w_folded = NIMFS::fold_witness(&w_lcccs, &w_cccs, bad_rho);
+            assert!(folded
+                .check_relation(&pedersen_params, &bad_w_folded)
+                .is_err());
+            let bad_folded =
+                NIMFS::fold(&lcccs_instances, &cccs_instances, &fold_proof, bad_rho).unwrap();
+            assert!(bad_folded
+                .check_relation(&pedersen_params, &w_folded)
+                .is_err());
+
+            // the sigmas and thetas are evaluations at another point than the r_x' of the folded
+            // instance
+            let bad_fold_proof = FoldProof {
+                r_x_prime: (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect(),
+                ..fold_proof.clone()
+            };
+            let bad_folded =
+                NIMFS::fold(&lcccs_instances, &cccs_instances, &bad_fold_proof, rho).unwrap();
+            assert!(matches!(
+                bad_folded.check_relation(&pedersen_params, &w_folded),
+                Err(CCSError::VEvaluationMismatch { .. })
+            ));
+        }
+    }
+
+    /// Folding with a single rho is the same as folding with the same rho_main and rho_v, while
+    /// distinct challenges fold C, u and x as rho_main does and v as rho_v does
+    #[test]
+    fn test_fold_with_challenges() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z_lcccs = vec![get_test_z(3), get_test_z(4)];
+        let z_cccs = vec![get_test_z(5)];
+        let (lcccs, w_lcccs): (Vec<_>, Vec<_>) = z_lcccs
+            .iter()
+            .map(|z| ccs.to_lcccs(&mut rng, &pedersen_params, z).unwrap())
+            .unzip();
+        let (cccs, w_cccs): (Vec<_>, Vec<_>) = z_cccs
+            .iter()
+            .map(|z| ccs.to_cccs(&mut rng, &pedersen_params, z).unwrap())
+            .unzip();
+
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &z_lcccs, &z_cccs, &r_x_prime).unwrap();
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime,
+        };
+        let (rho_main, rho_v) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+        let w_folded = NIMFS::fold_witness(&w_lcccs, &w_cccs, rho_main);
+
+        let folded = NIMFS::fold(&lcccs, &cccs, &fold_proof, rho_main).unwrap();
+        let folded_single = NIMFS::fold_with_challenges(
+            &lcccs,
+            &cccs,
+            &fold_proof,
+            &FoldChallenges::single(rho_main),
+        )
+        .unwrap();
+        assert_eq!(folded_single, folded);
+        folded_single
+            .check_relation(&pedersen_params, &w_folded)
+            .unwrap();
+
+        let challenges = FoldChallenges { rho_main, rho_v };
+        let folded_split =
+            NIMFS::fold_with_challenges(&lcccs, &cccs, &fold_proof, &challenges).unwrap();
+        let folded_v = NIMFS::fold(&lcccs, &cccs, &fold_proof, rho_v).unwrap();
+        assert_eq!(
+            (&folded_split.C, folded_split.u, &folded_split.x),
+            (&folded.C, folded.u, &folded.x)
+        );
+        assert_eq!(folded_split.v, folded_v.v);
+        // the v values are consistent with the witness folded with rho_main only if rho_v is
+        // rho_main
+        folded_split
+            .verify_fold_commitment(&pedersen_params, &w_folded)
+            .unwrap();
+        assert!(matches!(
+            folded_split.check_relation(&pedersen_params, &w_folded),
+            Err(CCSError::VEvaluationMismatch { .. })
+        ));
+    }
+
+    /// Folding instances of different CCS structures is rejected
+    #[test]
+    fn test_fold_incompatible_ccs() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let ccs_cubic = get_test_ccs_cubic::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let pedersen_params_cubic =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs_cubic.witness_len().unwrap());
+
+        let (lcccs, _) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let (cccs, _) = ccs_cubic
+            .to_cccs(&mut rng, &pedersen_params_cubic, &get_test_z_cubic(3))
+            .unwrap();
+
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let rho = Fr::rand(&mut rng);
+        let fold_proof = FoldProof {
+            sigmas: vec![lcccs.v.clone()],
+            thetas: vec![vec![Fr::zero(); ccs_cubic.t]],
+            r_x_prime,
+        };
+        let result = NIMFS::fold(&vec![lcccs], &vec![cccs], &fold_proof, rho);
+        assert!(matches!(result, Err(CCSError::IncompatibleCCS)));
+    }
+
+    /// Folding instances or sigmas and thetas of the wrong lengths is rejected instead of folding
+    /// truncated vectors
+    #[test]
+    fn test_fold_invalid_lengths() -> () {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+        let z_1 = get_test_z(3);
+        let z_2 = get_test_z(4);
+        let (lcccs, _) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
+        let (cccs, _) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
+
+        let r_x_prime: Vec<Fr> = (0..ccs.s).map(|_| Fr::rand(&mut rng)).collect();
+        let (sigmas, thetas) =
+            NIMFS::compute_sigmas_and_thetas(&ccs, &[z_1], &[z_2], &r_x_prime).unwrap();
+        let fold_proof = FoldProof {
+            sigmas,
+            thetas,
+            r_x_prime,
+        };
+        let rho = Fr::rand(&mut rng);
+        NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &fold_proof, rho).unwrap();
+
+        // no LCCCS instance to fold into
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.sigmas.clear();
+        assert!(matches!(
+            NIMFS::fold(&[], &[cccs.clone()], &bad_fold_proof, rho),
+            Err(CCSError::NoLCCCSInstances)
+        ));
+
+        // x lengths differ
+        let mut bad_cccs = cccs.clone();
+        bad_cccs.x.push(Fr::one());
+        assert!(matches!(
+            NIMFS::fold(&[lcccs.clone()], &[bad_cccs], &fold_proof, rho),
+            Err(CCSError::InvalidPublicInputLength {
+                expected: 1,
+                got: 2
+            })
+        ));
+
+        // sigmas and thetas lengths differ
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.thetas[0].pop();
+        assert!(matches!(
+            NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &bad_fold_proof, rho),
+            Err(CCSError::SigmaThetaLengthMismatch { t: 3, got: 2 })
+        ));
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.sigmas[0].push(Fr::one());
+        assert!(matches!(
+            NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &bad_fold_proof, rho),
+            Err(CCSError::SigmaThetaLengthMismatch { t: 3, got: 4 })
+        ));
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.sigmas.push(fold_proof.sigmas[0].clone());
+        assert!(matches!(
+            NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &bad_fold_proof, rho),
+            Err(CCSError::InvalidNumberOfSigmas { mu: 1, got: 2 })
+        ));
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.thetas.clear();
+        assert!(matches!(
+            NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &bad_fold_proof, rho),
+            Err(CCSError::InvalidNumberOfThetas { nu: 1, got: 0 })
+        ));
+
+        // r_x' is not of length s
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.r_x_prime.pop();
+        assert!(matches!(
+            NIMFS::fold(&[lcccs.clone()], &[cccs.clone()], &bad_fold_proof, rho),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 1
+            })
+        ));
+        let mut bad_fold_proof = fold_proof.clone();
+        bad_fold_proof.r_x_prime.push(Fr::rand(&mut rng));
+        assert!(matches!(
+            NIMFS::fold(&[lcccs], &[cccs], &bad_fold_proof, rho),
+            Err(CCSError::InvalidChallengeLength {
+                expected: 2,
+                got: 3
+            })
+        ));
+    }
+
     /// Perform multifolding of an LCCCS instance with a CCCS instance (as described in the paper)
     #[test]
     pub fn test_basic_multifolding() {
         let mut rng = test_rng();
 
         // Create a basic CCS circuit
-        let ccs = get_test_ccs::<G1Projective>();
-        let pedersen_params = Pedersen::new_params(&mut rng, ccs.n - ccs.l - 1);
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
 
         // Generate a satisfying witness
         let z_1 = get_test_z(3);
@@ -648,9 +1537,9 @@ pub mod test {
         let z_2 = get_test_z(4);
 
         // Create the LCCCS instance out of z_1
-        let (running_instance, w1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1);
+        let (running_instance, w1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
         // Create the CCCS instance out of z_2
-        let (new_instance, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2);
+        let (new_instance, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
 
         // Prover's transcript
         let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
@@ -663,7 +1552,8 @@ pub mod test {
             &vec![new_instance.clone()],
             &vec![w1],
             &vec![w2],
-        );
+        )
+        .unwrap();
 
         // Verifier's transcript
         let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
@@ -675,7 +1565,8 @@ pub mod test {
             &vec![running_instance.clone()],
             &vec![new_instance.clone()],
             proof,
-        );
+        )
+        .unwrap();
         assert_eq!(folded_lcccs, folded_lcccs_v);
 
         // Check that the folded LCCCS instance is a valid instance with respect to the folded witness
@@ -684,18 +1575,320 @@ pub mod test {
             .unwrap();
     }
 
+    /// Check that the prover returns an error instead of panicking on missing instances or
+    /// witnesses
+    #[test]
+    pub fn test_multifolding_prove_bad_inputs() {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (running_instance, w1) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let (new_instance, w2) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(4))
+            .unwrap();
+
+        let mut transcript = IOPTranscript::<Fr>::new(b"multifolding");
+        assert!(matches!(
+            NIMFS::prove(
+                &mut transcript,
+                &[],
+                &[new_instance.clone()],
+                &[],
+                &[w2.clone()]
+            ),
+            Err(MultifoldingError::EmptyInstances)
+        ));
+        assert!(matches!(
+            NIMFS::prove(
+                &mut transcript,
+                &[running_instance.clone()],
+                &[new_instance.clone()],
+                &[w1.clone()],
+                &[]
+            ),
+            Err(MultifoldingError::WitnessCountMismatch {
+                instances: 1,
+                witnesses: 0
+            })
+        ));
+
+        // an LCCCS whose v does not match its witness is an error, not a panic
+        let mut bad_instance = running_instance;
+        bad_instance.v[0] += Fr::one();
+        let mut transcript = IOPTranscript::<Fr>::new(b"multifolding");
+        assert!(matches!(
+            NIMFS::prove(
+                &mut transcript,
+                &[bad_instance],
+                &[new_instance],
+                &[w1],
+                &[w2]
+            ),
+            Err(MultifoldingError::ProverSumMismatch)
+        ));
+    }
+
+    /// Check that the verifier rejects a proof whose sigmas have been tampered with
+    #[test]
+    pub fn test_multifolding_bad_sigmas() {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let z_1 = get_test_z(3);
+        let z_2 = get_test_z(4);
+
+        let (running_instance, w1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
+        let (new_instance, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
+
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let (mut proof, _, _) = NIMFS::prove(
+            &mut transcript_p,
+            &vec![running_instance.clone()],
+            &vec![new_instance.clone()],
+            &vec![w1],
+            &vec![w2],
+        )
+        .unwrap();
+
+        // tamper with the proof
+        proof.sigmas[0][0] += Fr::one();
+
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_v.append_message(b"init", b"init").unwrap();
+        let result = NIMFS::verify(
+            &mut transcript_v,
+            &vec![running_instance],
+            &vec![new_instance],
+            proof,
+        );
+        assert!(matches!(
+            result,
+            Err(MultifoldingError::SumCheckClaimMismatch)
+        ));
+    }
+
+    /// Check that the verifier accepts an honest proof, and rejects it once one of the
+    /// coefficients of the sumcheck prover messages has been tampered with
+    #[test]
+    pub fn test_multifolding_bad_sumcheck() {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (running_instance, w1) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let (new_instance, w2) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(4))
+            .unwrap();
+
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let (proof, _, _) = NIMFS::prove(
+            &mut transcript_p,
+            &[running_instance.clone()],
+            &[new_instance.clone()],
+            &[w1],
+            &[w2],
+        )
+        .unwrap();
+
+        let verify = |proof: Proof<G1Projective>| {
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+            transcript_v.append_message(b"init", b"init").unwrap();
+            NIMFS::verify(
+                &mut transcript_v,
+                &[running_instance.clone()],
+                &[new_instance.clone()],
+                proof,
+            )
+        };
+        verify(proof.clone()).unwrap();
+
+        // p_1(0) + p_1(1) no longer matches the claimed sum
+        let mut bad_proof = proof.clone();
+        bad_proof.sc_proof.proofs[0].evaluations[0] += Fr::one();
+        assert!(matches!(verify(bad_proof), Err(VerifierError::SumCheck(_))));
+
+        // the rounds are consistent, but p_s(r_s) no longer matches the claim computed from the
+        // sigmas and thetas
+        let mut bad_proof = proof.clone();
+        let last_round = bad_proof.sc_proof.proofs.last_mut().unwrap();
+        *last_round.evaluations.last_mut().unwrap() += Fr::one();
+        assert!(matches!(
+            verify(bad_proof),
+            Err(VerifierError::SumCheckClaimMismatch)
+        ));
+    }
+
+    /// Check that the challenges are bound to the instances: verifying the proof against a
+    /// different CCCS instance should fail
+    #[test]
+    pub fn test_multifolding_instance_binding() {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (running_instance, w1) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let (new_instance, w2) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(4))
+            .unwrap();
+        let (other_instance, _) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(5))
+            .unwrap();
+
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let (proof, _, _) = NIMFS::prove(
+            &mut transcript_p,
+            &vec![running_instance.clone()],
+            &vec![new_instance],
+            &vec![w1],
+            &vec![w2],
+        )
+        .unwrap();
+
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_v.append_message(b"init", b"init").unwrap();
+        assert!(NIMFS::verify(
+            &mut transcript_v,
+            &vec![running_instance],
+            &vec![other_instance],
+            proof,
+        )
+        .is_err());
+    }
+
+    /// The challenges are bound to the CCS structure: the same instances of two CCS differing in a
+    /// single matrix entry are folded with different challenges, so a proof for one of them does
+    /// not verify for the other one
+    #[test]
+    pub fn test_multifolding_ccs_binding() {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let mut other_ccs = ccs.clone();
+        other_ccs.M[2].coeffs[0][0].0 += Fr::one();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (running_instance, w1) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let (new_instance, w2) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(4))
+            .unwrap();
+
+        // absorbing the two CCS gives different challenges
+        let mut transcript_1 = IOPTranscript::<Fr>::new(b"multifolding");
+        ccs.absorb_into(&mut transcript_1);
+        let mut transcript_2 = IOPTranscript::<Fr>::new(b"multifolding");
+        other_ccs.absorb_into(&mut transcript_2);
+        assert_ne!(
+            transcript_1.get_and_append_challenge(b"rho").unwrap(),
+            transcript_2.get_and_append_challenge(b"rho").unwrap()
+        );
+
+        // the instances only satisfy the first CCS, so only prove for that one
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let (proof, folded, _) = NIMFS::prove(
+            &mut transcript_p,
+            &[running_instance.clone()],
+            &[new_instance.clone()],
+            &[w1.clone()],
+            &[w2.clone()],
+        )
+        .unwrap();
+
+        // passing the digest of the CCS gives the same fold as hashing it in prove and verify
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let (_, folded_with_digest, _) = NIMFS::prove_with_ccs_digest(
+            &mut transcript_p,
+            &ccs.digest(),
+            &[running_instance.clone()],
+            &[new_instance.clone()],
+            &[w1],
+            &[w2],
+        )
+        .unwrap();
+        assert_eq!(folded_with_digest, folded);
+
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_v.append_message(b"init", b"init").unwrap();
+        assert_eq!(
+            NIMFS::verify(
+                &mut transcript_v,
+                &[running_instance.clone()],
+                &[new_instance.clone()],
+                proof.clone()
+            )
+            .unwrap(),
+            folded
+        );
+
+        // the proof does not verify against the digest of the other CCS
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_v.append_message(b"init", b"init").unwrap();
+        assert!(NIMFS::verify_with_ccs_digest(
+            &mut transcript_v,
+            &other_ccs.digest(),
+            &[running_instance.clone()],
+            &[new_instance.clone()],
+            proof.clone(),
+        )
+        .is_err());
+
+        // nor for the same instances relabeled with the other CCS
+        let running_instance = LCCCS {
+            ccs: Arc::new(other_ccs.clone()),
+            ..running_instance
+        };
+        let new_instance = CCCS {
+            ccs: Arc::new(other_ccs),
+            ..new_instance
+        };
+        let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_v.append_message(b"init", b"init").unwrap();
+        assert!(NIMFS::verify(
+            &mut transcript_v,
+            &[running_instance],
+            &[new_instance],
+            proof
+        )
+        .is_err());
+    }
+
     /// Perform multiple steps of multifolding of an LCCCS instance with a CCCS instance
     #[test]
     pub fn test_multifolding_two_instances_multiple_steps() {
         let mut rng = test_rng();
 
-        let ccs = get_test_ccs::<G1Projective>();
+        let ccs = get_test_ccs::<Fr>();
 
-        let pedersen_params = Pedersen::new_params(&mut rng, ccs.n - ccs.l - 1);
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
 
         // LCCCS witness
         let z_1 = get_test_z(2);
-        let (mut running_instance, mut w1) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_1);
+        let (mut running_instance, mut w1) =
+            ccs.to_lcccs(&mut rng, &pedersen_params, &z_1).unwrap();
 
         let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
         let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
@@ -710,7 +1903,7 @@ pub mod test {
             let z_2 = get_test_z(i);
             println!("z_2 {:?}", z_2); // DBG
 
-            let (new_instance, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2);
+            let (new_instance, w2) = ccs.to_cccs(&mut rng, &pedersen_params, &z_2).unwrap();
 
             // run the prover side of the multifolding
             let (proof, folded_lcccs, folded_witness) = NIMFS::prove(
@@ -719,7 +1912,8 @@ pub mod test {
                 &vec![new_instance.clone()],
                 &vec![w1],
                 &vec![w2],
-            );
+            )
+            .unwrap();
 
             // run the verifier side of the multifolding
             let folded_lcccs_v = NIMFS::verify(
@@ -727,7 +1921,8 @@ pub mod test {
                 &vec![running_instance.clone()],
                 &vec![new_instance.clone()],
                 proof,
-            );
+            )
+            .unwrap();
 
             assert_eq!(folded_lcccs, folded_lcccs_v);
 
@@ -742,14 +1937,100 @@ pub mod test {
         }
     }
 
+    /// Fold five CCCS instances one after the other, and verify the whole sequence at once
+    #[test]
+    pub fn test_multifolding_fold_all() {
+        let mut rng = test_rng();
+
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
+
+        let (running_instance, w1) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(2))
+            .unwrap();
+        let (new_instances, w_cccs): (Vec<_>, Vec<_>) = (3..8)
+            .map(|i| {
+                ccs.to_cccs(&mut rng, &pedersen_params, &get_test_z(i))
+                    .unwrap()
+            })
+            .unzip();
+
+        // prove the folds sequentially with the same transcript
+        let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript_p.append_message(b"init", b"init").unwrap();
+        let mut folded_lcccs = running_instance.clone();
+        let mut folded_witness = w1;
+        let mut proofs = Vec::new();
+        for (new_instance, w2) in new_instances.iter().zip(w_cccs) {
+            let (proof, lcccs, witness) = NIMFS::prove(
+                &mut transcript_p,
+                &[folded_lcccs],
+                &[new_instance.clone()],
+                &[folded_witness],
+                &[w2],
+            )
+            .unwrap();
+            proofs.push(proof);
+            folded_lcccs = lcccs;
+            folded_witness = witness;
+        }
+
+        let new_transcript = || {
+            let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
+            transcript_v.append_message(b"init", b"init").unwrap();
+            transcript_v
+        };
+        let folded_lcccs_v = NIMFS::fold_all(
+            &mut new_transcript(),
+            &running_instance,
+            &new_instances,
+            proofs.clone(),
+        )
+        .unwrap();
+        assert_eq!(folded_lcccs_v, folded_lcccs);
+        folded_lcccs_v
+            .check_relation(&pedersen_params, &folded_witness)
+            .unwrap();
+
+        // the proofs only verify in the order they were produced
+        let mut swapped_proofs = proofs.clone();
+        swapped_proofs.swap(1, 2);
+        assert!(NIMFS::fold_all(
+            &mut new_transcript(),
+            &running_instance,
+            &new_instances,
+            swapped_proofs
+        )
+        .is_err());
+
+        assert!(matches!(
+            NIMFS::fold_all(
+                &mut new_transcript(),
+                &running_instance,
+                &new_instances[1..],
+                proofs
+            ),
+            Err(MultifoldingError::ProofCountMismatch {
+                instances: 4,
+                proofs: 5
+            })
+        ));
+        assert!(matches!(
+            NIMFS::fold_all(&mut new_transcript(), &running_instance, &[], vec![]),
+            Err(MultifoldingError::EmptyInstances)
+        ));
+    }
+
     /// Test that generates mu>1 and nu>1 instances, and folds them in a single multifolding step.
     #[test]
     pub fn test_multifolding_mu_nu_instances() {
         let mut rng = test_rng();
 
         // Create a basic CCS circuit
-        let ccs = get_test_ccs::<G1Projective>();
-        let pedersen_params = Pedersen::new_params(&mut rng, ccs.n - ccs.l - 1);
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
 
         let mu = 10;
         let nu = 15;
@@ -770,7 +2051,9 @@ pub mod test {
         let mut lcccs_instances = Vec::new();
         let mut w_lcccs = Vec::new();
         for i in 0..mu {
-            let (running_instance, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_lcccs[i]);
+            let (running_instance, w) = ccs
+                .to_lcccs(&mut rng, &pedersen_params, &z_lcccs[i])
+                .unwrap();
             lcccs_instances.push(running_instance);
             w_lcccs.push(w);
         }
@@ -778,7 +2061,7 @@ pub mod test {
         let mut cccs_instances = Vec::new();
         let mut w_cccs = Vec::new();
         for i in 0..nu {
-            let (new_instance, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z_cccs[i]);
+            let (new_instance, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z_cccs[i]).unwrap();
             cccs_instances.push(new_instance);
             w_cccs.push(w);
         }
@@ -794,7 +2077,8 @@ pub mod test {
             &cccs_instances,
             &w_lcccs,
             &w_cccs,
-        );
+        )
+        .unwrap();
 
         // Verifier's transcript
         let mut transcript_v = IOPTranscript::<Fr>::new(b"multifolding");
@@ -802,7 +2086,7 @@ pub mod test {
 
         // Run the verifier side of the multifolding
         let folded_lcccs_v =
-            NIMFS::verify(&mut transcript_v, &lcccs_instances, &cccs_instances, proof);
+            NIMFS::verify(&mut transcript_v, &lcccs_instances, &cccs_instances, proof).unwrap();
         assert_eq!(folded_lcccs, folded_lcccs_v);
 
         // Check that the folded LCCCS instance is a valid instance with respect to the folded witness
@@ -818,8 +2102,9 @@ pub mod test {
         let mut rng = test_rng();
 
         // Create a basic CCS circuit
-        let ccs = get_test_ccs::<G1Projective>();
-        let pedersen_params = Pedersen::new_params(&mut rng, ccs.n - ccs.l - 1);
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params =
+            Pedersen::<G1Projective>::new_params(&mut rng, ccs.witness_len().unwrap());
 
         // Prover's transcript
         let mut transcript_p = IOPTranscript::<Fr>::new(b"multifolding");
@@ -852,7 +2137,9 @@ pub mod test {
             let mut lcccs_instances = Vec::new();
             let mut w_lcccs = Vec::new();
             for i in 0..mu {
-                let (running_instance, w) = ccs.to_lcccs(&mut rng, &pedersen_params, &z_lcccs[i]);
+                let (running_instance, w) = ccs
+                    .to_lcccs(&mut rng, &pedersen_params, &z_lcccs[i])
+                    .unwrap();
                 lcccs_instances.push(running_instance);
                 w_lcccs.push(w);
             }
@@ -860,7 +2147,8 @@ pub mod test {
             let mut cccs_instances = Vec::new();
             let mut w_cccs = Vec::new();
             for i in 0..nu {
-                let (new_instance, w) = ccs.to_cccs(&mut rng, &pedersen_params, &z_cccs[i]);
+                let (new_instance, w) =
+                    ccs.to_cccs(&mut rng, &pedersen_params, &z_cccs[i]).unwrap();
                 cccs_instances.push(new_instance);
                 w_cccs.push(w);
             }
@@ -872,11 +2160,12 @@ pub mod test {
                 &cccs_instances,
                 &w_lcccs,
                 &w_cccs,
-            );
+            )
+            .unwrap();
 
             // Run the verifier side of the multifolding
             let folded_lcccs_v =
-                NIMFS::verify(&mut transcript_v, &lcccs_instances, &cccs_instances, proof);
+                NIMFS::verify(&mut transcript_v, &lcccs_instances, &cccs_instances, proof).unwrap();
             assert_eq!(folded_lcccs, folded_lcccs_v);
 
             // Check that the folded LCCCS instance is a valid instance with respect to the folded witness
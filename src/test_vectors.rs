@@ -0,0 +1,266 @@
+//! Deterministic test vectors of a multifolding step, so that other implementations can check that
+//! they agree with this one, down to the encoding of the field elements and group points.
+//!
+//! An LCCCS and a CCCS instance of the Vitalik `x^3 + x + 5 == out` circuit of the CCS tests, with
+//! z = (1, x, out, x^2, x^3, x^3 + x), are folded with a rng seeded with [`TEST_VECTORS_SEED`] and
+//! Pedersen parameters generated from [`TEST_VECTORS_LABEL`]. All the field elements and group
+//! points are encoded as the hex of their compressed arkworks serialization: BLS12-381 scalars are
+//! 32 bytes in little-endian, and G1 points are 48 bytes in the zcash compressed format.
+//!
+//! The test vectors are only built in the tests, out of the test CCS and z vectors, and checked
+//! against the JSON committed in `src/test_vectors.json`, so that any change to the encoding or
+//! to the folding shows up as a failing test. After an intended change, regenerate the file with
+//! `UPDATE_TEST_VECTORS=1 cargo test test_vectors -- --include-ignored`.
+use ark_bls12_381::{Fr, G1Projective};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use transcript::IOPTranscript;
+
+use crate::ccs::cccs::{Witness, CCCS};
+use crate::ccs::ccs::test::{get_test_ccs, get_test_z};
+use crate::ccs::ccs::CCS;
+use crate::ccs::lcccs::LCCCS;
+use crate::ccs::pedersen::{Params, Pedersen};
+use crate::multifolding::{Multifolding, Proof};
+
+/// Seed of the rng sampling the commitment randomness and the r_x of the LCCCS instance
+pub const TEST_VECTORS_SEED: [u8; 32] = [42u8; 32];
+/// Label the Pedersen parameters are generated from, see [`Pedersen::new_params_transparent`]
+pub const TEST_VECTORS_LABEL: &[u8] = b"multifolding test vectors";
+/// Path of the committed JSON test vectors
+const TEST_VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_vectors.json");
+
+/// All the values involved in folding the test vector instances
+#[derive(Debug)]
+pub struct TestVectors {
+    pub ccs: CCS<Fr>,
+    pub pedersen_params: Params<G1Projective>,
+    pub lcccs: LCCCS<G1Projective>,
+    pub w_lcccs: Witness<Fr>,
+    pub cccs: CCCS<G1Projective>,
+    pub w_cccs: Witness<Fr>,
+    pub proof: Proof<G1Projective>,
+    pub rho: Fr,
+    pub folded_lcccs: LCCCS<G1Projective>,
+    pub folded_witness: Witness<Fr>,
+}
+
+impl TestVectors {
+    /// Fold the LCCCS of x = 3 with the CCCS of x = 4
+    pub fn new() -> Self {
+        let mut rng = ChaCha20Rng::from_seed(TEST_VECTORS_SEED);
+        let ccs = get_test_ccs::<Fr>();
+        let pedersen_params = Pedersen::<G1Projective>::new_params_transparent(
+            TEST_VECTORS_LABEL,
+            ccs.witness_len().unwrap(),
+        );
+
+        let (lcccs, w_lcccs) = ccs
+            .to_lcccs(&mut rng, &pedersen_params, &get_test_z(3))
+            .unwrap();
+        let (cccs, w_cccs) = ccs
+            .to_cccs(&mut rng, &pedersen_params, &get_test_z(4))
+            .unwrap();
+
+        let mut transcript = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript.append_message(b"init", b"init").unwrap();
+        let (proof, folded_lcccs, folded_witness) = Multifolding::<G1Projective>::prove(
+            &mut transcript,
+            &[lcccs.clone()],
+            &[cccs.clone()],
+            &[w_lcccs.clone()],
+            &[w_cccs.clone()],
+        )
+        .unwrap();
+        // the relaxation factor of the CCCS is 1, so the folded u is u + rho
+        let rho = folded_lcccs.u - lcccs.u;
+
+        TestVectors {
+            ccs,
+            pedersen_params,
+            lcccs,
+            w_lcccs,
+            cccs,
+            w_cccs,
+            proof,
+            rho,
+            folded_lcccs,
+            folded_witness,
+        }
+    }
+
+    /// Serialize the test vectors to JSON
+    pub fn to_json(&self) -> String {
+        let ccs = &self.ccs;
+        let M = json_list(ccs.M.iter().map(|M_j| {
+            // the nonzero entries as [row, col, value]
+            json_list(M_j.coeffs.iter().enumerate().flat_map(|(row, entries)| {
+                entries
+                    .iter()
+                    .map(move |(value, col)| format!("[{}, {}, {}]", row, col, hex(value)))
+            }))
+        }));
+        let S = json_list(
+            ccs.S
+                .iter()
+                .map(|S_i| json_list(S_i.iter().map(|j| j.to_string()))),
+        );
+        let ccs_json = json_object(&[
+            ("m", ccs.m.to_string()),
+            ("n", ccs.n.to_string()),
+            ("l", ccs.l.to_string()),
+            ("t", ccs.t.to_string()),
+            ("q", ccs.q.to_string()),
+            ("d", ccs.d.to_string()),
+            ("M", M),
+            ("S", S),
+            ("c", hex_list(&ccs.c)),
+        ]);
+
+        let sumcheck = json_object(&[
+            ("point", hex_list(&self.proof.sc_proof.point)),
+            (
+                "evaluations",
+                json_list(
+                    self.proof
+                        .sc_proof
+                        .proofs
+                        .iter()
+                        .map(|message| hex_list(&message.evaluations)),
+                ),
+            ),
+        ]);
+        let proof = json_object(&[
+            ("sumcheck", sumcheck),
+            (
+                "sigmas",
+                json_list(self.proof.sigmas.iter().map(|s| hex_list(s))),
+            ),
+            (
+                "thetas",
+                json_list(self.proof.thetas.iter().map(|t| hex_list(t))),
+            ),
+        ]);
+
+        json_object(&[
+            ("seed", format!("\"{}\"", to_hex(&TEST_VECTORS_SEED))),
+            (
+                "pedersen_label",
+                format!("\"{}\"", to_hex(TEST_VECTORS_LABEL)),
+            ),
+            (
+                "pedersen_generators",
+                hex_list(&self.pedersen_params.generators),
+            ),
+            ("ccs", ccs_json),
+            ("lcccs", lcccs_json(&self.lcccs)),
+            ("w_lcccs", witness_json(&self.w_lcccs)),
+            (
+                "cccs",
+                json_object(&[("C", hex(&self.cccs.C)), ("x", hex_list(&self.cccs.x))]),
+            ),
+            ("w_cccs", witness_json(&self.w_cccs)),
+            ("proof", proof),
+            ("rho", hex(&self.rho)),
+            ("folded_lcccs", lcccs_json(&self.folded_lcccs)),
+            ("folded_witness", witness_json(&self.folded_witness)),
+        ])
+    }
+}
+
+impl Default for TestVectors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn lcccs_json(lcccs: &LCCCS<G1Projective>) -> String {
+    json_object(&[
+        ("C", hex(&lcccs.C)),
+        ("u", hex(&lcccs.u)),
+        ("x", hex_list(&lcccs.x)),
+        ("r_x", hex_list(&lcccs.r_x)),
+        ("v", hex_list(&lcccs.v)),
+    ])
+}
+
+fn witness_json(w: &Witness<Fr>) -> String {
+    json_object(&[("w", hex_list(&w.w)), ("r_w", hex(&w.r_w))])
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The hex of the compressed serialization of the value, as a JSON string
+fn hex<T: CanonicalSerialize>(value: &T) -> String {
+    let mut bytes = vec![];
+    value.serialize_compressed(&mut bytes).unwrap();
+    format!("\"{}\"", to_hex(&bytes))
+}
+
+fn hex_list<T: CanonicalSerialize>(values: &[T]) -> String {
+    json_list(values.iter().map(hex))
+}
+
+fn json_list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<String>>().join(", "))
+}
+
+fn json_object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("\"{}\": {}", key, value))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The test vectors are a valid folding, and they are the same every time they are generated
+    #[test]
+    fn test_test_vectors() -> () {
+        let vectors = TestVectors::new();
+
+        let mut transcript = IOPTranscript::<Fr>::new(b"multifolding");
+        transcript.append_message(b"init", b"init").unwrap();
+        let folded_lcccs = Multifolding::<G1Projective>::verify(
+            &mut transcript,
+            &[vectors.lcccs.clone()],
+            &[vectors.cccs.clone()],
+            vectors.proof,
+        )
+        .unwrap();
+        assert_eq!(folded_lcccs, vectors.folded_lcccs);
+        folded_lcccs
+            .check_relation(&vectors.pedersen_params, &vectors.folded_witness)
+            .unwrap();
+
+        let json = TestVectors::new().to_json();
+        assert_eq!(json, TestVectors::new().to_json());
+        assert!(json.contains(&format!("\"rho\": {}", hex(&vectors.rho))));
+    }
+
+    /// The test vectors match the committed ones, down to the last byte
+    // XXX src/test_vectors.json is not committed yet. Generate it with
+    // `UPDATE_TEST_VECTORS=1 cargo test test_vectors -- --include-ignored`, commit it and remove
+    // the ignore.
+    #[test]
+    #[ignore = "src/test_vectors.json has not been generated yet"]
+    fn test_test_vectors_golden() -> () {
+        let json = TestVectors::new().to_json();
+        if std::env::var_os("UPDATE_TEST_VECTORS").is_some() {
+            std::fs::write(TEST_VECTORS_PATH, format!("{}\n", json)).unwrap();
+        }
+        let golden = std::fs::read_to_string(TEST_VECTORS_PATH).unwrap_or_else(|_| {
+            panic!(
+                "{} is missing, generate it with UPDATE_TEST_VECTORS=1",
+                TEST_VECTORS_PATH
+            )
+        });
+        assert_eq!(json, golden.trim_end());
+    }
+}
@@ -0,0 +1,44 @@
+/// Some basic field utilities
+use ark_ff::Field;
+
+/// Compute base^exp by square-and-multiply, taking O(log exp) multiplications instead of the exp
+/// multiplications of a naive loop, so that the powers of rho stay cheap when folding many
+/// instances
+pub fn pow<F: Field>(base: F, exp: u64) -> F {
+    let mut result = F::one();
+    let mut square = base;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result *= square;
+        }
+        square.square_in_place();
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_std::{test_rng, One, UniformRand, Zero};
+
+    #[test]
+    fn test_pow() {
+        let mut rng = test_rng();
+        let base = Fr::rand(&mut rng);
+
+        for exp in [0u64, 1, 2, 3, 7, 8, 100, 1023, 1024] {
+            let mut naive = Fr::one();
+            for _ in 0..exp {
+                naive *= base;
+            }
+            assert_eq!(pow(base, exp), naive);
+        }
+
+        assert_eq!(pow(base, u64::MAX), base.pow([u64::MAX]));
+        assert_eq!(pow(Fr::zero(), 0), Fr::one());
+        assert_eq!(pow(Fr::zero(), 5), Fr::zero());
+    }
+}
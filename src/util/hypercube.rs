@@ -2,33 +2,63 @@
 use crate::espresso::virtual_polynomial::bit_decompose;
 use ark_ff::PrimeField;
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+
+/// Bit order of the points of a boolean hypercube
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// The i-th point is the little-endian bit representation of i (first coordinate is the least
+    /// significant bit). This is the order of the evaluations of a DenseMultilinearExtension.
+    Little,
+    /// The i-th point is the big-endian bit representation of i (first coordinate is the most
+    /// significant bit)
+    Big,
+}
 
 /// A boolean hypercube that returns its points as an iterator
 /// If you iterate on it for 3 variables you will get points in little-endian order:
 /// 000 -> 100 -> 010 -> 110 -> 001 -> 101 -> 011 -> 111
+///
+/// The MLEs of z and of the matrices (see `util::mle`) are indexed in little-endian order, so the
+/// i-th point of the default hypercube corresponds to the i-th entry of z or the i-th row of a
+/// matrix.
 #[derive(Debug)]
 pub struct BooleanHypercube<F: PrimeField> {
     _f: PhantomData<F>,
     n_vars: usize,
+    endianness: Endianness,
     current: u64,
     max: u64,
 }
 
 impl<F: PrimeField> BooleanHypercube<F> {
+    /// Return a hypercube iterating its points in little-endian order
     pub fn new(n_vars: usize) -> Self {
+        Self::with_endianness(n_vars, Endianness::Little)
+    }
+
+    pub fn with_endianness(n_vars: usize, endianness: Endianness) -> Self {
         BooleanHypercube::<F> {
             _f: PhantomData::<F>,
             n_vars,
+            endianness,
             current: 0,
             max: 2_u32.pow(n_vars as u32) as u64,
         }
     }
 
-    /// returns the entry at given i (which is the little-endian bit representation of i)
+    /// returns the entry at given i (which is the bit representation of i in the hypercube
+    /// endianness)
     pub fn at_i(&self, i: usize) -> Vec<F> {
         assert!(i < self.max as usize);
-        let bits = bit_decompose((i) as u64, self.n_vars);
+        self.point(i as u64)
+    }
+
+    fn point(&self, i: u64) -> Vec<F> {
+        let mut bits = bit_decompose(i, self.n_vars);
+        if self.endianness == Endianness::Big {
+            bits.reverse();
+        }
         bits.iter().map(|&x| F::from(x)).collect()
     }
 }
@@ -37,8 +67,7 @@ impl<F: PrimeField> Iterator for BooleanHypercube<F> {
     type Item = Vec<F>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let bits = bit_decompose(self.current, self.n_vars);
-        let result: Vec<F> = bits.iter().map(|&x| F::from(x)).collect();
+        let result = self.point(self.current);
         self.current += 1;
 
         if self.current > self.max {
@@ -73,4 +102,18 @@ mod test {
             assert_eq!(point, expected_results[i], "Failed at iteration {}", i);
         }
     }
+
+    #[test]
+    fn test_hypercube_endianness() {
+        let point_3 = vec![Fr::one(), Fr::one(), Fr::zero()];
+
+        let bhc_le = BooleanHypercube::<Fr>::with_endianness(3, Endianness::Little);
+        assert_eq!(bhc_le.at_i(3), point_3);
+        assert_eq!(BooleanHypercube::<Fr>::new(3).nth(3).unwrap(), point_3);
+
+        let bhc_be = BooleanHypercube::<Fr>::with_endianness(3, Endianness::Big);
+        let point_3_be = vec![Fr::zero(), Fr::one(), Fr::one()];
+        assert_eq!(bhc_be.at_i(3), point_3_be);
+        assert_eq!(bhc_be.into_iter().nth(3).unwrap(), point_3_be);
+    }
 }
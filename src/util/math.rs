@@ -0,0 +1,54 @@
+/// Integer helpers to derive the dimensions of the CCS
+use crate::ccs::ccs::CCSError;
+
+/// Return log2(n) if n is a power of two, and `CCSError::NotPowerOfTwo` otherwise
+pub fn log2_exact(n: usize) -> Result<usize, CCSError> {
+    if !n.is_power_of_two() {
+        return Err(CCSError::NotPowerOfTwo { n });
+    }
+    Ok(n.trailing_zeros() as usize)
+}
+
+/// Return the number of variables of an MLE over n values, that is log2 of the power of two that
+/// n gets padded to. Return `CCSError::DimensionTooLarge` if n can't be padded to a power of two
+/// in a usize, instead of overflowing.
+pub fn log2_ceil(n: usize) -> Result<usize, CCSError> {
+    let padded = n
+        .checked_next_power_of_two()
+        .ok_or(CCSError::DimensionTooLarge { n })?;
+    log2_exact(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::log2;
+
+    #[test]
+    fn test_log2_exact() {
+        for i in 0..usize::BITS as usize {
+            assert_eq!(log2_exact(1 << i).unwrap(), i);
+        }
+        for n in [0, 3, 5, 6, 7, 9, 1023, (1 << 20) + 1, usize::MAX] {
+            assert!(matches!(
+                log2_exact(n),
+                Err(CCSError::NotPowerOfTwo { n: got }) if got == n
+            ));
+        }
+    }
+
+    #[test]
+    fn test_log2_ceil() {
+        for n in 0..1025 {
+            assert_eq!(log2_ceil(n).unwrap(), log2(n) as usize);
+        }
+        assert_eq!(
+            log2_ceil(1 << (usize::BITS - 1)).unwrap(),
+            usize::BITS as usize - 1
+        );
+        assert!(matches!(
+            log2_ceil((1 << (usize::BITS - 1)) + 1),
+            Err(CCSError::DimensionTooLarge { .. })
+        ));
+    }
+}
@@ -1,5 +1,5 @@
 /// Some basic MLE utilities
-use ark_ff::PrimeField;
+use ark_ff::{Field, PrimeField};
 use ark_poly::DenseMultilinearExtension;
 use ark_std::log2;
 
@@ -40,11 +40,14 @@ pub fn matrix_to_mle<F: PrimeField>(matrix: Matrix<F>) -> DenseMultilinearExtens
     vec_to_mle(n_vars, &M_evals)
 }
 
+/// Return the MLE whose evaluations over the boolean hypercube {0,1}^n_vars (in little-endian
+/// order) are the entries of v, padded with zeros. This is the multilinear extension used for z and
+/// for the M_j * z vectors by compute_Ls, compute_q and compute_v_j.
 pub fn vec_to_mle<F: PrimeField>(n_vars: usize, v: &Vec<F>) -> DenseMultilinearExtension<F> {
     // Pad to 2^n_vars
     let v_padded: Vec<F> = [
         v.clone(),
-        std::iter::repeat(F::zero())
+        core::iter::repeat(F::zero())
             .take((1 << n_vars) - v.len())
             .collect(),
     ]
@@ -52,6 +55,92 @@ pub fn vec_to_mle<F: PrimeField>(n_vars: usize, v: &Vec<F>) -> DenseMultilinearE
     DenseMultilinearExtension::<F>::from_evaluations_vec(n_vars, v_padded)
 }
 
+/// Evaluate at the given point the MLE whose evaluations over the boolean hypercube (in
+/// little-endian order) are the entries of evals, padded with zeros up to 2^point.len() entries.
+///
+/// This gives the same result as `vec_to_mle(point.len(), evals).evaluate(point)`, but folds the
+/// evaluations table one variable at a time instead of building the MLE.
+///
+/// The point may be over an extension field E of the field of the evaluations, in which case the
+/// evaluations are lifted into E, and the result is the evaluation of the same MLE seen over E.
+///
+/// # Panics
+///
+/// Panics if evals has more than 2^point.len() entries, as they would not fit in the hypercube of
+/// the point. Callers evaluating M_j * z vectors check the length of the point against s first.
+pub fn eval_mle_at<F: PrimeField, E: Field<BasePrimeField = F>>(evals: &[F], point: &[E]) -> E {
+    assert!(evals.len() <= 1 << point.len());
+    let mut table: Vec<E> = evals.iter().map(|e| E::from_base_prime_field(*e)).collect();
+    table.resize(1 << point.len(), E::zero());
+
+    // the first variable is the least significant bit of the index, so fixing it combines the
+    // consecutive pairs of evaluations
+    for r_i in point {
+        table = table
+            .chunks(2)
+            .map(|pair| pair[0] + *r_i * (pair[1] - pair[0]))
+            .collect();
+    }
+    table[0]
+}
+
+/// Return the table of the Lagrange basis polynomials of the boolean hypercube evaluated at the
+/// given point, that is eq(i, point) for all the points i of the hypercube in little-endian order.
+///
+/// Evaluating an MLE at the point is the inner product of its evaluations with this table, and the
+/// table of a point of the hypercube is the unit vector of that point.
+pub fn lagrange_basis_at<F: PrimeField>(point: &[F]) -> Vec<F> {
+    let mut table = vec![F::one()];
+    for r_i in point {
+        // the new variable is the most significant bit of the index so far
+        table = [
+            table
+                .iter()
+                .map(|t| *t * (F::one() - r_i))
+                .collect::<Vec<F>>(),
+            table.iter().map(|t| *t * r_i).collect(),
+        ]
+        .concat();
+    }
+    table
+}
+
+/// The multilinear extension of an evaluation table over the boolean hypercube {0,1}^n_vars, in
+/// little-endian order, missing entries being zeros. It is evaluated by folding the table with
+/// [`eval_mle_at`], so the point may be over an extension field of the field of the table.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DenseMultilinear<F: PrimeField> {
+    n_vars: usize,
+    evals: Vec<F>,
+}
+
+impl<F: PrimeField> DenseMultilinear<F> {
+    /// Wrap the evaluation table of a multilinear polynomial in n_vars variables
+    ///
+    /// # Panics
+    ///
+    /// Panics if evals has more than 2^n_vars entries.
+    pub fn from_evaluations(n_vars: usize, evals: Vec<F>) -> Self {
+        assert!(evals.len() <= 1 << n_vars);
+        DenseMultilinear { n_vars, evals }
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.n_vars
+    }
+
+    /// Evaluate the polynomial at the point, which on a point of the hypercube gives back the entry
+    /// of the table at its index
+    ///
+    /// # Panics
+    ///
+    /// Panics if the point does not have n_vars coordinates.
+    pub fn evaluate<E: Field<BasePrimeField = F>>(&self, point: &[E]) -> E {
+        assert_eq!(point.len(), self.n_vars);
+        eval_mle_at(&self.evals, point)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,7 +151,7 @@ mod tests {
         util::{hypercube::BooleanHypercube, vec::to_F_matrix},
     };
     use ark_poly::MultilinearExtension;
-    use ark_std::Zero;
+    use ark_std::{test_rng, One, UniformRand, Zero};
 
     use ark_bls12_381::Fr;
 
@@ -118,6 +207,68 @@ mod tests {
         }
     }
 
+    /// Check that evaluating the MLE at a random point is the same as folding its evaluation table
+    /// one variable at a time
+    #[test]
+    fn test_vec_to_mle_evaluate() {
+        let mut rng = test_rng();
+
+        let z = get_test_z::<Fr>(3);
+        let z_mle = vec_to_mle(3, &z);
+
+        let r: Vec<Fr> = (0..z_mle.num_vars).map(|_| Fr::rand(&mut rng)).collect();
+        let mut folded = z_mle.clone();
+        for r_i in r.iter() {
+            folded = fix_variables(&folded, &[*r_i]);
+        }
+        assert_eq!(folded.num_vars, 0);
+        assert_eq!(folded.evaluations[0], z_mle.evaluate(&r).unwrap());
+    }
+
+    #[test]
+    fn test_eval_mle_at() {
+        let mut rng = test_rng();
+
+        let z = get_test_z::<Fr>(3);
+        let z_mle = vec_to_mle(3, &z);
+
+        // over the boolean hypercube, it gives back the (padded) entries of z
+        for (i, x) in BooleanHypercube::new(3).enumerate() {
+            let z_i = z.get(i).copied().unwrap_or(Fr::zero());
+            assert_eq!(eval_mle_at(&z, &x), z_i);
+            let expected_basis: Vec<Fr> = (0..8)
+                .map(|j| if i == j { Fr::one() } else { Fr::zero() })
+                .collect();
+            assert_eq!(lagrange_basis_at(&x), expected_basis);
+        }
+
+        // and at any other point it matches the MLE and the Lagrange interpolation
+        let r: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let lagrange_eval: Fr = lagrange_basis_at(&r)
+            .iter()
+            .zip(z.iter())
+            .map(|(l_i, z_i)| *l_i * z_i)
+            .sum();
+        assert_eq!(eval_mle_at(&z, &r), z_mle.evaluate(&r).unwrap());
+        assert_eq!(eval_mle_at(&z, &r), lagrange_eval);
+    }
+
+    #[test]
+    fn test_dense_multilinear() {
+        let mut rng = test_rng();
+
+        let z = get_test_z::<Fr>(3);
+        let z_mle = DenseMultilinear::from_evaluations(3, z.clone());
+        assert_eq!(z_mle.num_vars(), 3);
+
+        // over the boolean hypercube, it gives back the raw table entries, and zeros past them
+        for (i, x) in BooleanHypercube::new(3).enumerate() {
+            assert_eq!(z_mle.evaluate(&x), z.get(i).copied().unwrap_or(Fr::zero()));
+        }
+        let r: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        assert_eq!(z_mle.evaluate(&r), vec_to_mle(3, &z).evaluate(&r).unwrap());
+    }
+
     #[test]
     fn test_fix_variables() {
         let A = to_F_matrix(vec![
@@ -1,3 +1,6 @@
+pub mod field;
 pub mod hypercube;
+pub mod math;
 pub mod mle;
+pub mod univariate;
 pub mod vec;
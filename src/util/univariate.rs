@@ -0,0 +1,149 @@
+/// A dense univariate polynomial, such as the per-round polynomials of the sumcheck
+use ark_ff::PrimeField;
+use core::ops::Add;
+
+/// Univariate polynomial given by its coefficients, from the constant term up to the leading
+/// coefficient. The sumcheck over g(x) sends one such polynomial per round, of degree at most
+/// d + 1 for a CCS of degree d.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnivariatePoly<F: PrimeField> {
+    pub coeffs: Vec<F>,
+}
+
+impl<F: PrimeField> UnivariatePoly<F> {
+    /// Build the polynomial out of its coefficients, dropping the leading zeros
+    pub fn new(coeffs: Vec<F>) -> Self {
+        let mut poly = UnivariatePoly { coeffs };
+        poly.trim();
+        poly
+    }
+
+    /// Interpolate the polynomial of degree at most evals.len() - 1 taking the value evals[i] at
+    /// i, for i in 0..evals.len(). These are the points the sumcheck prover evaluates its round
+    /// polynomials at, so a polynomial of degree d + 1 is given by its d + 2 evaluations.
+    pub fn from_evaluations(evals: &[F]) -> Self {
+        let points: Vec<F> = (0..evals.len()).map(|i| F::from(i as u64)).collect();
+
+        let mut coeffs = vec![F::zero(); evals.len()];
+        for (i, eval_i) in evals.iter().enumerate() {
+            // Lagrange basis polynomial \prod_{j != i} (X - j) / (i - j)
+            let mut basis = vec![F::one()];
+            let mut denominator = F::one();
+            for (j, point_j) in points.iter().enumerate().filter(|(j, _)| *j != i) {
+                // multiply the basis by (X - j)
+                let mut shifted = vec![F::zero(); basis.len() + 1];
+                for (k, b_k) in basis.iter().enumerate() {
+                    shifted[k + 1] += b_k;
+                    shifted[k] -= *b_k * point_j;
+                }
+                basis = shifted;
+                denominator *= points[i] - points[j];
+            }
+
+            let scale = *eval_i * denominator.inverse().unwrap();
+            for (c_k, b_k) in coeffs.iter_mut().zip(basis.iter()) {
+                *c_k += scale * b_k;
+            }
+        }
+        Self::new(coeffs)
+    }
+
+    /// Return the degree of the polynomial, taking the zero polynomial to be of degree 0
+    pub fn degree(&self) -> usize {
+        self.coeffs.len().saturating_sub(1)
+    }
+
+    /// Evaluate the polynomial at x using Horner's rule
+    pub fn evaluate(&self, x: &F) -> F {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(F::zero(), |acc, c_i| acc * x + c_i)
+    }
+
+    fn trim(&mut self) {
+        while let Some(c) = self.coeffs.last() {
+            if !c.is_zero() {
+                break;
+            }
+            self.coeffs.pop();
+        }
+    }
+}
+
+impl<F: PrimeField> Add for &UnivariatePoly<F> {
+    type Output = UnivariatePoly<F>;
+
+    fn add(self, other: &UnivariatePoly<F>) -> UnivariatePoly<F> {
+        let mut coeffs = vec![F::zero(); self.coeffs.len().max(other.coeffs.len())];
+        for (i, c_i) in self.coeffs.iter().enumerate() {
+            coeffs[i] += c_i;
+        }
+        for (i, c_i) in other.coeffs.iter().enumerate() {
+            coeffs[i] += c_i;
+        }
+        UnivariatePoly::new(coeffs)
+    }
+}
+
+impl<F: PrimeField> Add for UnivariatePoly<F> {
+    type Output = UnivariatePoly<F>;
+
+    fn add(self, other: UnivariatePoly<F>) -> UnivariatePoly<F> {
+        &self + &other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::espresso::sum_check::verifier::interpolate_uni_poly;
+    use ark_bls12_381::Fr;
+    use ark_std::{test_rng, UniformRand, Zero};
+
+    #[test]
+    fn test_univariate_poly() {
+        let mut rng = test_rng();
+
+        // p(X) = 3 + 2X + X^2 + 5X^3
+        let p = UnivariatePoly::new(vec![
+            Fr::from(3u64),
+            Fr::from(2u64),
+            Fr::from(1u64),
+            Fr::from(5u64),
+        ]);
+        assert_eq!(p.degree(), 3);
+        assert_eq!(p.evaluate(&Fr::from(2u64)), Fr::from(51u64));
+
+        // interpolating the evaluations at 0..=d gives back the polynomial, and extra evaluations
+        // do not raise its degree
+        for n_evals in 4..7 {
+            let evals: Vec<Fr> = (0..n_evals)
+                .map(|i| p.evaluate(&Fr::from(i as u64)))
+                .collect();
+            assert_eq!(UnivariatePoly::from_evaluations(&evals), p);
+
+            let x = Fr::rand(&mut rng);
+            assert_eq!(p.evaluate(&x), interpolate_uni_poly(&evals, x).unwrap());
+        }
+
+        // q(X) = 1 - 2X - X^2 - 5X^3, so p + q = 4
+        let q = UnivariatePoly::new(vec![
+            Fr::from(1u64),
+            -Fr::from(2u64),
+            -Fr::from(1u64),
+            -Fr::from(5u64),
+        ]);
+        let sum = &p + &q;
+        assert_eq!(sum, UnivariatePoly::new(vec![Fr::from(4u64)]));
+        assert_eq!(sum.degree(), 0);
+        assert_eq!(p.clone() + q.clone(), sum);
+
+        let x = Fr::rand(&mut rng);
+        assert_eq!((&p + &p).evaluate(&x), p.evaluate(&x) + p.evaluate(&x));
+        assert_eq!(
+            UnivariatePoly::<Fr>::from_evaluations(&[Fr::zero(); 3]).coeffs,
+            vec![]
+        );
+    }
+}
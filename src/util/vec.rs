@@ -3,15 +3,56 @@
 /// Stole a bunch of code from Alex in https://github.com/alex-ozdemir/bulletproofs
 /// and wrote some lame tests for it
 use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::cfg_iter;
 
 use rayon::iter::IndexedParallelIterator;
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
 
-/// A sparse representation of constraint matrices.
+/// A dense representation of constraint matrices, as a list of rows.
 pub type Matrix<F> = Vec<Vec<F>>;
 
+/// A sparse representation of constraint matrices. Each row is a list of (value, column) pairs
+/// holding the nonzero entries of that row.
+#[derive(Debug, Clone, Eq, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SparseMatrix<F: PrimeField> {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    pub coeffs: Vec<Vec<(F, usize)>>,
+}
+
+impl<F: PrimeField> SparseMatrix<F> {
+    /// Build a sparse matrix out of the nonzero entries of a dense one
+    pub fn from_dense(M: &Matrix<F>) -> Self {
+        let coeffs = M
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, value)| !value.is_zero())
+                    .map(|(col, value)| (*value, col))
+                    .collect()
+            })
+            .collect();
+        SparseMatrix {
+            n_rows: M.len(),
+            n_cols: M[0].len(),
+            coeffs,
+        }
+    }
+
+    pub fn to_dense(&self) -> Matrix<F> {
+        let mut M = vec![vec![F::zero(); self.n_cols]; self.n_rows];
+        for (row, row_coeffs) in self.coeffs.iter().enumerate() {
+            for &(value, col) in row_coeffs.iter() {
+                M[row][col] = value;
+            }
+        }
+        M
+    }
+}
+
 /// Hadamard product between two vectors
 pub fn hadamard<F: PrimeField>(a: &Vec<F>, b: &Vec<F>) -> Vec<F> {
     cfg_iter!(a).zip(b).map(|(a, b)| *a * b).collect()
@@ -31,6 +72,14 @@ pub fn mat_vec_mul<F: PrimeField>(mat: &Matrix<F>, vec: &[F]) -> Vec<F> {
     result
 }
 
+// Multiply sparse matrix by vector, only touching the nonzero entries of the matrix
+pub fn mat_vec_mul_sparse<F: PrimeField>(M: &SparseMatrix<F>, z: &[F]) -> Vec<F> {
+    assert_eq!(M.n_cols, z.len());
+    cfg_iter!(M.coeffs)
+        .map(|row| row.iter().map(|&(value, col)| value * z[col]).sum())
+        .collect()
+}
+
 // Multiply vector by scalar
 pub fn vec_scalar_mul<F: PrimeField>(vec: &[F], c: &F) -> Vec<F> {
     let mut result = vec![F::zero(); vec.len()];
@@ -129,4 +178,26 @@ mod test {
             vec![Fr::from(806u64), Fr::from(2762u64), Fr::from(2656u64)]
         );
     }
+
+    #[test]
+    fn test_sparse_matrix() -> () {
+        let A = to_F_matrix::<Fr>(vec![
+            vec![0, 0, 4, 0],
+            vec![1, 0, 0, 2],
+            vec![0, 0, 0, 0],
+            vec![0, 3, 0, 5],
+        ]);
+        let A_sparse = SparseMatrix::from_dense(&A);
+        assert_eq!(A_sparse.n_rows, 4);
+        assert_eq!(A_sparse.n_cols, 4);
+        // only the nonzero entries are stored
+        assert_eq!(
+            A_sparse.coeffs.iter().map(|row| row.len()).sum::<usize>(),
+            5
+        );
+        assert_eq!(A_sparse.to_dense(), A);
+
+        let v = to_F_vec::<Fr>(vec![19, 55, 50, 3]);
+        assert_eq!(mat_vec_mul_sparse(&A_sparse, &v), mat_vec_mul(&A, &v));
+    }
 }